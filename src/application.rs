@@ -4,7 +4,14 @@ use std::{
 };
 
 use physics_engine::{
-    body::Body, constants::MILLISECS_PER_FRAME, my_texture::MyTexture, shape::Shape, vec2::Vec2,
+    body::Body,
+    constants::{MILLISECS_PER_FRAME, PIXELS_PER_METER},
+    constraint::{Joint, MouseJoint},
+    event::Event as WorldEvent,
+    my_texture::MyTexture,
+    real::Real,
+    shape::Shape,
+    vec2::Vec2,
     world::World,
 };
 use rand::Rng;
@@ -33,6 +40,7 @@ pub struct Application {
     wind: bool,
     polygon: bool,
     world: World,
+    mouse_joint: Option<usize>,
     crate_texture: Texture,
     basketball_texture: Texture,
     bowlingball_texture: Texture,
@@ -76,6 +84,7 @@ impl Application {
             gravity: true,
             polygon: false,
             world,
+            mouse_joint: None,
             crate_texture,
             basketball_texture,
             bowlingball_texture,
@@ -105,12 +114,27 @@ impl Application {
                     x, y, mouse_btn, ..
                 } => match mouse_btn {
                     MouseButton::Left => {
-                        let mut rng = rand::thread_rng();
-                        let r: f64 = rng.gen();
-                        if r > 0.5 {
-                            self.world.add_body(Body::basketball(x as f32, y as f32));
+                        let mouse = Vec2::new(x as Real, y as Real);
+                        // Grab the top-most dynamic body under the cursor, otherwise spawn a ball
+                        let grabbed = self
+                            .world
+                            .bodies()
+                            .filter(|(_, body)| !body.is_static && body.contains_point(mouse))
+                            .last();
+
+                        if let Some((handle, body)) = grabbed {
+                            let max_force = 1000. * body.mass * PIXELS_PER_METER;
+                            let anchor = body.world_to_local(mouse);
+                            let joint = MouseJoint::new(handle, anchor, mouse, max_force);
+                            self.mouse_joint = Some(self.world.add_joint(joint));
                         } else {
-                            self.world.add_body(Body::bowlingball(x as f32, y as f32));
+                            let mut rng = rand::thread_rng();
+                            let r: f64 = rng.gen();
+                            if r > 0.5 {
+                                self.world.add_body(Body::basketball(x as Real, y as Real));
+                            } else {
+                                self.world.add_body(Body::bowlingball(x as Real, y as Real));
+                            }
                         }
                     }
                     MouseButton::Right => {
@@ -122,14 +146,34 @@ impl Application {
                             Vec2::new(40., 20.),
                         ];
                         if self.polygon {
-                            let p = Body::new(Shape::Polygon(v), x as f32, y as f32, 1., None);
-                            self.world.add_body(p)
+                            let p = Body::new(Shape::Polygon(v), x as Real, y as Real, 1., None);
+                            self.world.add_body(p);
                         } else {
-                            self.world.add_body(Body::crate_(x as f32, y as f32));
+                            self.world.add_body(Body::crate_(x as Real, y as Real));
                         }
                     }
                     _ => {}
                 },
+                Event::MouseMotion { x, y, .. } => {
+                    if let Some(index) = self.mouse_joint {
+                        if let Joint::Mouse(joint) = &mut self.world.joints[index] {
+                            joint.target = Vec2::new(x as Real, y as Real);
+                            // body_mut wakes the body, it may have fallen asleep while the
+                            // mouse was still
+                            let body = joint.body;
+                            self.world.body_mut(body);
+                        }
+                    }
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } => {
+                    // Releasing keeps the body's velocity, so it gets flung
+                    if let Some(index) = self.mouse_joint.take() {
+                        self.world.remove_joint(index);
+                    }
+                }
                 _ => {}
             }
         }
@@ -149,97 +193,118 @@ impl Application {
 
         let now = SystemTime::now();
 
-        let delta_time_ms = now
+        let elapsed = now
             .duration_since(self.time_previous_frame)
             .unwrap()
-            .as_millis();
-
-        let dt = f32::min(delta_time_ms as f32 / 1000., 0.016);
+            .as_secs_f64() as Real;
 
-        self.world.update(dt, self.gravity, self.wind);
+        self.world.step(elapsed, self.gravity, self.wind);
         self.time_previous_frame = now;
+
+        // Breaking or removing joints shifts the index of the mouse joint
+        for event in self.world.events() {
+            let (WorldEvent::JointBroken { index, .. } | WorldEvent::JointRemoved { index, .. }) =
+                event
+            else {
+                continue;
+            };
+            match self.mouse_joint {
+                Some(mouse) if *index < mouse => self.mouse_joint = Some(mouse - 1),
+                Some(mouse) if *index == mouse => self.mouse_joint = None,
+                _ => {}
+            }
+        }
     }
 
     /* --------------------------------- Render --------------------------------- */
 
     pub fn render(&mut self) {
-        // Draw bodies
+        // Draw bodies between their last two positions, so they move smoothly whether or not
+        // the world stepped this frame
+        let alpha = self.world.alpha();
         for body in self.world.get_bodies() {
-            let color = if body.is_colliding && self.debug {
+            let pos = body.interpolated_pos(alpha);
+            let rotation = body.interpolated_rotation(alpha);
+            // Sleeping bodies are greyed out
+            let color = if !self.debug {
+                Color::WHITE
+            } else if !body.is_awake && !body.is_static {
+                Color::GREY
+            } else if body.is_colliding {
                 Color::RED
             } else {
                 Color::WHITE
             };
             match body.shape {
                 Shape::Circle(radius) => {
-                    if self.debug || body.texture.is_none() {
-                        graphics::draw_circle(
-                            body.pos.x as i16,
-                            body.pos.y as i16,
-                            radius as i16,
-                            body.rotation,
-                            color,
-                            &mut self.canvas,
-                        );
-                    } else {
-                        let texture = match body.texture.unwrap() {
+                    if let (false, Some(texture)) = (self.debug, body.texture) {
+                        let texture = match texture {
                             MyTexture::Crate => &self.crate_texture,
                             MyTexture::Metal => &self.metal_texture,
                             MyTexture::BowlingBall => &self.bowlingball_texture,
                             MyTexture::BasketBall => &self.basketball_texture,
                         };
                         graphics::draw_texture(
-                            body.pos.x as i32,
-                            body.pos.y as i32,
+                            pos.x as i32,
+                            pos.y as i32,
                             radius as u32 * 2,
                             radius as u32 * 2,
-                            body.rotation,
+                            rotation,
                             texture,
                             &mut self.canvas,
                         )
-                    }
-                }
-                Shape::Box(width, height) => {
-                    if self.debug || body.texture.is_none() {
-                        graphics::draw_polygon(
-                            body.pos.x as i16,
-                            body.pos.y as i16,
-                            body.shape.get_world_verticies(body.rotation, body.pos),
+                    } else {
+                        graphics::draw_circle(
+                            pos.x as i16,
+                            pos.y as i16,
+                            radius as i16,
+                            rotation,
                             color,
                             &mut self.canvas,
                         );
-                    } else {
-                        let texture = match body.texture.unwrap() {
+                    }
+                }
+                Shape::Box(width, height) => {
+                    if let (false, Some(texture)) = (self.debug, body.texture) {
+                        let texture = match texture {
                             MyTexture::Crate => &self.crate_texture,
                             MyTexture::Metal => &self.metal_texture,
                             MyTexture::BowlingBall => &self.bowlingball_texture,
                             MyTexture::BasketBall => &self.basketball_texture,
                         };
                         graphics::draw_texture(
-                            body.pos.x as i32,
-                            body.pos.y as i32,
+                            pos.x as i32,
+                            pos.y as i32,
                             width as u32,
                             height as u32,
-                            body.rotation,
+                            rotation,
                             texture,
                             &mut self.canvas,
                         );
+                    } else {
+                        graphics::draw_polygon(
+                            pos.x as i16,
+                            pos.y as i16,
+                            body.shape.get_world_verticies(rotation, pos),
+                            color,
+                            &mut self.canvas,
+                        );
                     }
                 }
                 Shape::Polygon(_) => {
                     if !self.debug {
                         graphics::draw_fill_polygon(
-                            body.pos.x as i16,
-                            body.pos.y as i16,
-                            body.shape.get_world_verticies(body.rotation, body.pos),
+                            pos.x as i16,
+                            pos.y as i16,
+                            body.shape.get_world_verticies(rotation, pos),
                             color,
                             &mut self.canvas,
                         );
                     } else {
                         graphics::draw_polygon(
-                            body.pos.x as i16,
-                            body.pos.y as i16,
-                            body.shape.get_world_verticies(body.rotation, body.pos),
+                            pos.x as i16,
+                            pos.y as i16,
+                            body.shape.get_world_verticies(rotation, pos),
                             color,
                             &mut self.canvas,
                         );
@@ -248,6 +313,22 @@ impl Application {
             }
         }
 
+        // Draw the mouse joint while dragging
+        if let Some(index) = self.mouse_joint {
+            if let Joint::Mouse(joint) = &self.world.joints[index] {
+                let body = self.world.body(joint.body).unwrap();
+                let anchor = body.local_to_world(joint.local_anchor);
+                graphics::draw_line(
+                    anchor.x as i16,
+                    anchor.y as i16,
+                    joint.target.x as i16,
+                    joint.target.y as i16,
+                    Color::YELLOW,
+                    &mut self.canvas,
+                );
+            }
+        }
+
         self.canvas.present();
     }
 
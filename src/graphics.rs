@@ -1,4 +1,4 @@
-use physics_engine::vec2::Vec2;
+use physics_engine::{real::Real, vec2::Vec2};
 use sdl2::{
     gfx::primitives::DrawRenderer,
     pixels::Color,
@@ -20,16 +20,16 @@ pub fn draw_circle(
     x: i16,
     y: i16,
     radius: i16,
-    angle: f32,
+    angle: Real,
     color: Color,
     canvas: &mut Canvas<Window>,
 ) {
     // This was previously causing issues since I was casting angle.cos() to i16. This lost
-    // precision and roudned it down to zero. Doing the calculations with f32s fixes this.
-    let d_x = radius as f32 * angle.cos();
-    let d_y = radius as f32 * angle.sin();
-    let x2 = x as f32 + d_x;
-    let y2 = y as f32 + d_y;
+    // precision and roudned it down to zero. Doing the calculations with floats fixes this.
+    let d_x = radius as Real * angle.cos();
+    let d_y = radius as Real * angle.sin();
+    let x2 = x as Real + d_x;
+    let y2 = y as Real + d_y;
 
     canvas.circle(x, y, radius, color).unwrap();
     canvas.line(x, y, x2 as i16, y2 as i16, color).unwrap();
@@ -39,7 +39,7 @@ pub fn draw_fill_circle(
     x: i16,
     y: i16,
     radius: i16,
-    _angle: f32,
+    _angle: Real,
     color: Color,
     canvas: &mut Canvas<Window>,
 ) {
@@ -121,7 +121,7 @@ pub fn draw_texture(
     y: i32,
     width: u32,
     height: u32,
-    rotation: f32,
+    rotation: Real,
     texture: &Texture,
     canvas: &mut Canvas<Window>,
 ) {
@@ -131,16 +131,8 @@ pub fn draw_texture(
         width,
         height,
     );
-    let rotation_deg = rotation * 57.2958;
+    let rotation_deg: Real = rotation * 57.2958;
     canvas
-        .copy_ex(
-            &texture,
-            None,
-            rect,
-            rotation_deg as f64,
-            None,
-            false,
-            false,
-        )
+        .copy_ex(texture, None, rect, rotation_deg as f64, None, false, false)
         .unwrap();
 }
@@ -1,5 +1,6 @@
 extern crate sdl2;
 mod application;
+#[allow(dead_code)]
 mod graphics;
 mod sdl;
 
@@ -0,0 +1,70 @@
+use super::{real::Real, vec2::Vec2};
+
+// Axis aligned bounding box, used by the broadphase to find bodies that might be touching
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Aabb { min, max }
+    }
+
+    // The smallest box around all the points
+    pub fn from_points(points: &[Vec2]) -> Self {
+        let mut min = Vec2::new(Real::MAX, Real::MAX);
+        let mut max = Vec2::new(Real::MIN, Real::MIN);
+        for point in points {
+            min = Vec2::new(min.x.min(point.x), min.y.min(point.y));
+            max = Vec2::new(max.x.max(point.x), max.y.max(point.y));
+        }
+        Aabb { min, max }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && self.max.x >= other.max.x
+            && self.max.y >= other.max.y
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        self.min.x <= point.x
+            && self.max.x >= point.x
+            && self.min.y <= point.y
+            && self.max.y >= point.y
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: Vec2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Vec2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        }
+    }
+
+    // Grown by margin on every side
+    pub fn fattened(&self, margin: Real) -> Aabb {
+        let margin = Vec2::new(margin, margin);
+        Aabb {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn perimeter(&self) -> Real {
+        2. * (self.max.x - self.min.x + self.max.y - self.min.y)
+    }
+}
@@ -1,26 +1,40 @@
-use super::{my_texture::MyTexture, shape::Shape, vec2::Vec2};
+use super::{
+    aabb::Aabb, filter::Filter, my_texture::MyTexture, real::Real, shape::Shape, vec2::Vec2,
+};
 
 #[derive(Clone)]
 pub struct Body {
     pub shape: Shape,
     pub is_colliding: bool,
     pub is_static: bool,
-    pub restitution: f32,
-    pub friction: f32,
+    // Swept against static bodies every update so it can't tunnel through them when fast
+    pub bullet: bool,
+    // Passes through everything, reporting the bodies it overlaps with events instead
+    pub sensor: bool,
+    // Sleeping bodies aren't moved until something wakes them. Static bodies are never awake.
+    pub is_awake: bool,
+    // How long the body has been close to still
+    pub sleep_time: Real,
+    pub restitution: Real,
+    pub friction: Real,
+    pub filter: Filter,
 
     pub pos: Vec2,
+    // Where the body was before the last update, to interpolate from when rendering
+    pub previous_pos: Vec2,
     pub vel: Vec2,
     pub acc: Vec2,
-    pub mass: f32,
-    pub inv_mass: f32,
+    pub mass: Real,
+    pub inv_mass: Real,
     pub net_force: Vec2,
 
-    pub rotation: f32,
-    pub ang_vel: f32,
-    pub ang_acc: f32,
-    pub inertia: f32,
-    pub inv_inertia: f32,
-    pub net_torque: f32,
+    pub rotation: Real,
+    pub previous_rotation: Real,
+    pub ang_vel: Real,
+    pub ang_acc: Real,
+    pub inertia: Real,
+    pub inv_inertia: Real,
+    pub net_torque: Real,
 
     pub texture: Option<MyTexture>,
 }
@@ -28,31 +42,45 @@ pub struct Body {
 impl Body {
     pub fn new<T: Into<Option<MyTexture>>>(
         shape: Shape,
-        x: f32,
-        y: f32,
-        mass: f32,
+        x: Real,
+        y: Real,
+        mass: Real,
         texture: T,
     ) -> Self {
-        let inertia = shape.calc_inertia(mass);
-        let inv_inertia = if inertia > 0. { 1. / inertia } else { 0. };
         let inv_mass = if mass != 0. { 1. / mass } else { 0. };
 
         let epsilon = 0.00005;
         let is_static = inv_mass < epsilon;
 
+        // Polygons report a fixed inertia regardless of mass, so static bodies have to be
+        // zeroed here or the constraint solver would still spin them.
+        let inertia = shape.calc_inertia(mass);
+        let inv_inertia = if inertia > 0. && !is_static {
+            1. / inertia
+        } else {
+            0.
+        };
+
         Body {
             shape,
             is_colliding: false,
             is_static,
+            bullet: false,
+            sensor: false,
+            is_awake: !is_static,
+            sleep_time: 0.,
             restitution: 1.0,
             friction: 0.7,
+            filter: Filter::default(),
             pos: Vec2::new(x, y),
+            previous_pos: Vec2::new(x, y),
             vel: Vec2::new(0., 0.),
             acc: Vec2::new(0., 0.),
             mass,
             inv_mass,
             net_force: Vec2::new(0., 0.),
             rotation: 0.,
+            previous_rotation: 0.,
             ang_vel: 0.,
             ang_acc: 0.,
             inertia,
@@ -62,77 +90,90 @@ impl Body {
         }
     }
 
-    pub fn basketball(x: f32, y: f32) -> Self {
-        let mut b = Body::new(
-            Shape::Circle(30.),
-            x as f32,
-            y as f32,
-            1.,
-            MyTexture::BasketBall,
-        );
+    pub fn basketball(x: Real, y: Real) -> Self {
+        let mut b = Body::new(Shape::Circle(30.), x, y, 1., MyTexture::BasketBall);
         b.restitution = 0.8;
         b.friction = 0.5;
         b
     }
 
-    pub fn bowlingball(x: f32, y: f32) -> Self {
-        let mut b = Body::new(
-            Shape::Circle(20.),
-            x as f32,
-            y as f32,
-            10.,
-            MyTexture::BowlingBall,
-        );
+    pub fn bowlingball(x: Real, y: Real) -> Self {
+        let mut b = Body::new(Shape::Circle(20.), x, y, 10., MyTexture::BowlingBall);
         b.restitution = 0.2;
         b.friction = 0.2;
         b
     }
 
-    pub fn crate_(x: f32, y: f32) -> Self {
-        let mut b = Body::new(
-            Shape::Box(75., 75.),
-            x as f32,
-            y as f32,
-            1.,
-            MyTexture::Crate,
-        );
+    pub fn crate_(x: Real, y: Real) -> Self {
+        let mut b = Body::new(Shape::Box(75., 75.), x, y, 1., MyTexture::Crate);
         b.restitution = 0.2;
         b.friction = 0.2;
         b
     }
 
-    pub fn update(&mut self, dt: f32) {
-        if self.is_static {
+    pub fn update(&mut self, dt: Real) {
+        self.integrate_forces(dt);
+        self.integrate_velocities(dt);
+    }
+
+    // The world splits integration in two so constraints can correct the velocities
+    // before they are used to move the body. It integrates the forces with its own
+    // Integrator, this is the semi-implicit Euler one for bodies updated on their own.
+    pub fn integrate_forces(&mut self, dt: Real) {
+        if self.is_static || !self.is_awake {
             return;
         }
         self.integrate_linear(dt);
         self.integrate_angular(dt);
     }
 
-    fn integrate_linear(&mut self, dt: f32) {
+    pub fn integrate_velocities(&mut self, dt: Real) {
+        if self.is_static || !self.is_awake {
+            return;
+        }
+        self.pos += self.vel * dt;
+        self.rotation += self.ang_vel * dt;
+    }
+
+    fn integrate_linear(&mut self, dt: Real) {
         self.acc = self.net_force * self.inv_mass;
         self.vel += self.acc * dt;
-        self.pos += self.vel * dt;
 
         self.clear_forces();
     }
 
-    fn integrate_angular(&mut self, dt: f32) {
-        self.ang_acc += self.net_torque * self.inv_inertia;
+    fn integrate_angular(&mut self, dt: Real) {
+        self.ang_acc = self.net_torque * self.inv_inertia;
         self.ang_vel += self.ang_acc * dt;
-        self.rotation += self.ang_vel * dt;
 
         self.clear_torque();
     }
 
+    // Applying a force or torque wakes the body
     pub fn add_force(&mut self, force: Vec2) {
+        self.wake();
         self.net_force += force;
     }
 
-    pub fn add_torque(&mut self, torque: f32) {
+    pub fn add_torque(&mut self, torque: Real) {
+        self.wake();
         self.net_torque += torque;
     }
 
+    pub fn wake(&mut self) {
+        if !self.is_static {
+            self.is_awake = true;
+            self.sleep_time = 0.;
+        }
+    }
+
+    // Stops the body where it is. The sleep_time is kept.
+    pub(crate) fn sleep(&mut self) {
+        self.is_awake = false;
+        self.vel = Vec2::new(0., 0.);
+        self.ang_vel = 0.;
+    }
+
     pub fn apply_impulse(&mut self, impulse: Vec2, r: Vec2) {
         if self.is_static {
             return;
@@ -141,12 +182,66 @@ impl Body {
         self.ang_vel += r.cross(impulse) * self.inv_inertia;
     }
 
-    fn clear_forces(&mut self) {
+    // Velocity of a point on the body, r is relative to the body's position
+    pub fn velocity_at(&self, r: Vec2) -> Vec2 {
+        self.vel + Vec2::new(-self.ang_vel * r.y, self.ang_vel * r.x)
+    }
+
+    // Converts a world point into the body's local space, e.g. for joint anchors
+    pub fn world_to_local(&self, point: Vec2) -> Vec2 {
+        (point - self.pos).rotate(-self.rotation)
+    }
+
+    pub fn local_to_world(&self, point: Vec2) -> Vec2 {
+        point.rotate(self.rotation) + self.pos
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        match self.shape {
+            Shape::Circle(radius) => (point - self.pos).magnitude_squared() <= radius * radius,
+            Shape::Polygon(_) | Shape::Box(_, _) => {
+                let vertices = self.shape.get_world_verticies(self.rotation, self.pos);
+                // Inside a convex polygon means being behind every edge normal
+                (0..vertices.len()).all(|i| {
+                    let normal = self.shape.edge_at(i, self.rotation, self.pos).normal();
+                    (point - vertices[i]).dot(normal) <= 0.
+                })
+            }
+        }
+    }
+
+    // Between where the body was before the last update and where it is now, alpha being
+    // World::alpha
+    pub fn interpolated_pos(&self, alpha: Real) -> Vec2 {
+        self.previous_pos + (self.pos - self.previous_pos) * alpha
+    }
+
+    pub fn interpolated_rotation(&self, alpha: Real) -> Real {
+        self.previous_rotation + (self.rotation - self.previous_rotation) * alpha
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        self.shape.aabb(self.rotation, self.pos)
+    }
+
+    pub(crate) fn clear_forces(&mut self) {
         self.net_force.x = 0.;
         self.net_force.y = 0.;
     }
 
-    fn clear_torque(&mut self) {
+    pub(crate) fn clear_torque(&mut self) {
         self.net_torque = 0.;
     }
 }
+
+// Two mutable bodies from the same slice, the same split_at_mut trick used in World
+pub(crate) fn get_pair_mut(bodies: &mut [Body], a: usize, b: usize) -> (&mut Body, &mut Body) {
+    assert!(a != b, "Body can't be paired with itself");
+    if a < b {
+        let (left, right) = bodies.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = bodies.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
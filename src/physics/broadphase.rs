@@ -0,0 +1,38 @@
+mod dynamic_tree;
+mod spatial_hash;
+mod sweep_and_prune;
+
+pub use dynamic_tree::DynamicTree;
+pub use spatial_hash::SpatialHash;
+pub use sweep_and_prune::SweepAndPrune;
+
+use crate::{aabb::Aabb, body::Body};
+
+// Finds the pairs of bodies that might be touching, so the world only runs the exact
+// collision tests on those. Bodies are referred to by their index in world.bodies.
+pub trait BroadPhase {
+    // Called with the bodies where they are now before the world next needs pairs or a query,
+    // if bodies have been added, moved or removed since the last update. New bodies are at the
+    // end of bodies.
+    fn update(&mut self, bodies: &[Body]);
+
+    // The body at index was removed from world.bodies and the last body moved into its place,
+    // like Vec::swap_remove. Only called when both were in the last update. Broadphases that
+    // keep anything per body follow it here, instead of starting over in the next update.
+    // Nothing to do for one rebuilt every update.
+    fn remove(&mut self, _index: usize) {}
+
+    // Every pair whose bounding boxes overlap, in any order. Extra pairs are fine, they just
+    // cost an exact test, but a missing pair means those bodies pass through each other. The
+    // world sorts the pairs before using them, so the pairs have to depend only on the bodies
+    // for the simulation to be deterministic, not the order they come out in.
+    fn pairs(&self, bodies: &[Body]) -> Vec<(usize, usize)>;
+
+    // Every body whose bounding box overlaps aabb, as of the last update, for queries like
+    // World::raycast. Extra bodies are fine here too. Without a faster way, every box is tested.
+    fn query_aabb(&self, aabb: &Aabb, bodies: &[Body]) -> Vec<usize> {
+        (0..bodies.len())
+            .filter(|&index| bodies[index].aabb().overlaps(aabb))
+            .collect()
+    }
+}
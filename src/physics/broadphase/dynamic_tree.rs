@@ -0,0 +1,292 @@
+use crate::{aabb::Aabb, body::Body, constants::AABB_MARGIN};
+
+use super::BroadPhase;
+
+// Bounding volume hierarchy over the bodies' fattened boxes, kept balanced as leaves move
+// around. A body only has to be reinserted once it leaves its fattened box, which bodies at
+// rest or moving slowly rarely do.
+pub struct DynamicTree {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    root: Option<usize>,
+    // The leaf node of each body, by index into world.bodies
+    leaves: Vec<usize>,
+}
+
+struct Node {
+    aabb: Aabb,
+    parent: Option<usize>,
+    children: Option<[usize; 2]>,
+    // Only leaves have a body
+    body: Option<usize>,
+    // Leaves are 0
+    height: i32,
+}
+
+impl DynamicTree {
+    pub fn new() -> Self {
+        DynamicTree {
+            nodes: vec![],
+            free: vec![],
+            root: None,
+            leaves: vec![],
+        }
+    }
+
+    // Calls callback with every body whose fattened box overlaps aabb, until it returns false
+    pub fn query<F: FnMut(usize) -> bool>(&self, aabb: &Aabb, mut callback: F) {
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.aabb.overlaps(aabb) {
+                continue;
+            }
+            match (node.children, node.body) {
+                (Some(children), _) => stack.extend(children),
+                (None, Some(body)) => {
+                    if !callback(body) {
+                        return;
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    fn allocate(&mut self, node: Node) -> usize {
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let Some(root) = self.root else {
+            self.root = Some(leaf);
+            self.nodes[leaf].parent = None;
+            return;
+        };
+
+        // Walk down to the sibling that grows the tree's total perimeter the least
+        let leaf_aabb = self.nodes[leaf].aabb;
+        let mut index = root;
+        while let Some([child_1, child_2]) = self.nodes[index].children {
+            let perimeter = self.nodes[index].aabb.perimeter();
+            let combined = self.nodes[index].aabb.union(&leaf_aabb).perimeter();
+
+            // Cost of making a new parent for this node and the leaf
+            let cost = 2. * combined;
+            // Cost of pushing the leaf further down, every node above grows
+            let inheritance = 2. * (combined - perimeter);
+
+            let child_cost = |child: usize| {
+                let node = &self.nodes[child];
+                let grown = node.aabb.union(&leaf_aabb).perimeter();
+                match node.children {
+                    None => grown + inheritance,
+                    Some(_) => grown - node.aabb.perimeter() + inheritance,
+                }
+            };
+            let cost_1 = child_cost(child_1);
+            let cost_2 = child_cost(child_2);
+
+            if cost < cost_1 && cost < cost_2 {
+                break;
+            }
+            index = if cost_1 < cost_2 { child_1 } else { child_2 };
+        }
+
+        let sibling = index;
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate(Node {
+            aabb: leaf_aabb.union(&self.nodes[sibling].aabb),
+            parent: old_parent,
+            children: Some([sibling, leaf]),
+            body: None,
+            height: self.nodes[sibling].height + 1,
+        });
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        match old_parent {
+            Some(parent) => self.replace_child(parent, sibling, new_parent),
+            None => self.root = Some(new_parent),
+        }
+
+        self.refit_from(self.nodes[leaf].parent);
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+
+        // The leaf's parent goes too, the sibling takes its place
+        let parent = self.nodes[leaf].parent.unwrap();
+        let [child_1, child_2] = self.nodes[parent].children.unwrap();
+        let sibling = if child_1 == leaf { child_2 } else { child_1 };
+        let grand_parent = self.nodes[parent].parent;
+
+        self.nodes[sibling].parent = grand_parent;
+        self.free.push(parent);
+        match grand_parent {
+            Some(grand_parent) => {
+                self.replace_child(grand_parent, parent, sibling);
+                self.refit_from(Some(grand_parent));
+            }
+            None => self.root = Some(sibling),
+        }
+    }
+
+    fn replace_child(&mut self, parent: usize, old: usize, new: usize) {
+        if let Some(children) = &mut self.nodes[parent].children {
+            for child in children {
+                if *child == old {
+                    *child = new;
+                }
+            }
+        }
+    }
+
+    // Rebalances and recomputes the boxes of every node from index up to the root
+    fn refit_from(&mut self, mut index: Option<usize>) {
+        while let Some(i) = index {
+            let i = self.balance(i);
+            let [child_1, child_2] = self.nodes[i].children.unwrap();
+            self.nodes[i].aabb = self.nodes[child_1].aabb.union(&self.nodes[child_2].aabb);
+            self.nodes[i].height = 1 + self.nodes[child_1].height.max(self.nodes[child_2].height);
+            index = self.nodes[i].parent;
+        }
+    }
+
+    // Rotates the taller child of a up if the children's heights differ by more than one.
+    // Returns the node now in a's place.
+    fn balance(&mut self, a: usize) -> usize {
+        let Some([b, c]) = self.nodes[a].children else {
+            return a;
+        };
+        if self.nodes[a].height < 2 {
+            return a;
+        }
+
+        let balance = self.nodes[c].height - self.nodes[b].height;
+        if balance > 1 {
+            self.rotate(a, c, b)
+        } else if balance < -1 {
+            self.rotate(a, b, c)
+        } else {
+            a
+        }
+    }
+
+    fn rotate(&mut self, a: usize, up: usize, other: usize) -> usize {
+        let [f, g] = self.nodes[up].children.unwrap();
+
+        let parent = self.nodes[a].parent;
+        self.nodes[up].parent = parent;
+        self.nodes[a].parent = Some(up);
+        match parent {
+            Some(parent) => self.replace_child(parent, a, up),
+            None => self.root = Some(up),
+        }
+
+        // up keeps its taller child and hands the other to a
+        let (keep, give) = if self.nodes[f].height > self.nodes[g].height {
+            (f, g)
+        } else {
+            (g, f)
+        };
+        self.nodes[up].children = Some([a, keep]);
+        self.nodes[a].children = Some([other, give]);
+        self.nodes[give].parent = Some(a);
+
+        self.nodes[a].aabb = self.nodes[other].aabb.union(&self.nodes[give].aabb);
+        self.nodes[a].height = 1 + self.nodes[other].height.max(self.nodes[give].height);
+        self.nodes[up].aabb = self.nodes[a].aabb.union(&self.nodes[keep].aabb);
+        self.nodes[up].height = 1 + self.nodes[a].height.max(self.nodes[keep].height);
+        up
+    }
+}
+
+impl BroadPhase for DynamicTree {
+    // Adds leaves for new bodies and moves the ones that escaped their fattened box
+    fn update(&mut self, bodies: &[Body]) {
+        // Bodies were removed without telling the tree, the indices have moved so start over
+        if bodies.len() < self.leaves.len() {
+            *self = DynamicTree::new();
+        }
+
+        for (index, body) in bodies.iter().enumerate() {
+            let aabb = body.aabb();
+            match self.leaves.get(index) {
+                Some(&leaf) => {
+                    if !self.nodes[leaf].aabb.contains(&aabb) {
+                        self.remove_leaf(leaf);
+                        self.nodes[leaf].aabb = aabb.fattened(AABB_MARGIN);
+                        self.insert_leaf(leaf);
+                    }
+                }
+                None => {
+                    let leaf = self.allocate(Node {
+                        aabb: aabb.fattened(AABB_MARGIN),
+                        parent: None,
+                        children: None,
+                        body: Some(index),
+                        height: 0,
+                    });
+                    self.insert_leaf(leaf);
+                    self.leaves.push(leaf);
+                }
+            }
+        }
+    }
+
+    // Frees the body's leaf, the last body's leaf takes over its index like in world.bodies
+    fn remove(&mut self, index: usize) {
+        let leaf = self.leaves.swap_remove(index);
+        self.remove_leaf(leaf);
+        self.free.push(leaf);
+        if let Some(&moved) = self.leaves.get(index) {
+            self.nodes[moved].body = Some(index);
+        }
+    }
+
+    // Static bodies only look for dynamic ones, pairs of static bodies never need solving
+    fn pairs(&self, bodies: &[Body]) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        for (i, &leaf) in self.leaves.iter().enumerate() {
+            if bodies[i].is_static {
+                continue;
+            }
+            self.query(&self.nodes[leaf].aabb, |j| {
+                // Dynamic pairs are found from both sides, only keep one
+                if j != i && (bodies[j].is_static || j > i) {
+                    pairs.push((i, j));
+                }
+                true
+            });
+        }
+        pairs
+    }
+
+    fn query_aabb(&self, aabb: &Aabb, _bodies: &[Body]) -> Vec<usize> {
+        let mut found = vec![];
+        self.query(aabb, |body| {
+            found.push(body);
+            true
+        });
+        found
+    }
+}
+
+impl Default for DynamicTree {
+    fn default() -> Self {
+        DynamicTree::new()
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+
+use crate::{aabb::Aabb, body::Body, real::Real, vec2::Vec2};
+
+use super::BroadPhase;
+
+// Bodies covering more cells than this skip the grid and are tested against every body, so
+// a long floor doesn't fill hundreds of cells
+const MAX_CELLS_PER_BODY: i64 = 64;
+
+// Uniform grid of square cells, hashed so only the cells with bodies in them are stored. Each
+// body goes in every cell its box touches and only bodies sharing a cell are paired up. The
+// grid is rebuilt every update, which is cheap when the bodies are many and similar in size,
+// like a pile of balls. A cell size around the diameter of the typical body works best.
+pub struct SpatialHash {
+    cell_size: Real,
+    boxes: Vec<Aabb>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    // Bodies too big for the grid
+    large: Vec<usize>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: Real) -> Self {
+        assert!(cell_size > 0., "Spatial hash cell size must be positive");
+        SpatialHash {
+            cell_size,
+            boxes: vec![],
+            cells: HashMap::new(),
+            large: vec![],
+        }
+    }
+
+    pub fn cell_size(&self) -> Real {
+        self.cell_size
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+impl BroadPhase for SpatialHash {
+    fn update(&mut self, bodies: &[Body]) {
+        // Emptied rather than dropped, so the cells keep their allocations
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.large.clear();
+        self.boxes = bodies.iter().map(Body::aabb).collect();
+
+        for (index, aabb) in self.boxes.iter().enumerate() {
+            let (min_x, min_y) = self.cell(aabb.min);
+            let (max_x, max_y) = self.cell(aabb.max);
+            if cell_count(min_x, min_y, max_x, max_y) > MAX_CELLS_PER_BODY {
+                self.large.push(index);
+                continue;
+            }
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    self.cells.entry((x, y)).or_default().push(index);
+                }
+            }
+        }
+
+        self.cells.retain(|_, cell| !cell.is_empty());
+    }
+
+    fn pairs(&self, bodies: &[Body]) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        let is_pair = |i: usize, j: usize| {
+            !(bodies[i].is_static && bodies[j].is_static) && self.boxes[i].overlaps(&self.boxes[j])
+        };
+
+        for (&cell, indices) in &self.cells {
+            for (k, &i) in indices.iter().enumerate() {
+                for &j in &indices[k + 1..] {
+                    if !is_pair(i, j) {
+                        continue;
+                    }
+                    // Two bodies can share several cells, only the cell holding the corner of
+                    // their overlap reports them
+                    let a = &self.boxes[i];
+                    let b = &self.boxes[j];
+                    let corner = Vec2::new(a.min.x.max(b.min.x), a.min.y.max(b.min.y));
+                    if self.cell(corner) == cell {
+                        pairs.push((i, j));
+                    }
+                }
+            }
+        }
+
+        for (k, &i) in self.large.iter().enumerate() {
+            for j in 0..bodies.len() {
+                // Two large bodies are only paired once
+                if i != j && !self.large[..=k].contains(&j) && is_pair(i, j) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+
+        // The cells come out of the map in a different order every run
+        pairs.sort_unstable();
+        pairs
+    }
+
+    fn query_aabb(&self, aabb: &Aabb, _bodies: &[Body]) -> Vec<usize> {
+        let (min_x, min_y) = self.cell(aabb.min);
+        let (max_x, max_y) = self.cell(aabb.max);
+        // A big area is quicker to check box by box, like a large body
+        let cells = cell_count(min_x, min_y, max_x, max_y);
+        let mut found: Vec<usize> = if cells > self.cells.len() as i64 {
+            (0..self.boxes.len()).collect()
+        } else {
+            let mut found = self.large.clone();
+            for x in min_x..=max_x {
+                for y in min_y..=max_y {
+                    found.extend(self.cells.get(&(x, y)).into_iter().flatten());
+                }
+            }
+            found
+        };
+
+        // A body is in every cell its box touches
+        found.sort_unstable();
+        found.dedup();
+        found.retain(|&index| self.boxes[index].overlaps(aabb));
+        found
+    }
+}
+
+// Cells from min to max inclusive. In i64, a tiny cell size can put far more cells than an i32
+// holds under a single body.
+fn cell_count(min_x: i32, min_y: i32, max_x: i32, max_y: i32) -> i64 {
+    (max_x as i64 - min_x as i64 + 1) * (max_y as i64 - min_y as i64 + 1)
+}
@@ -0,0 +1,85 @@
+use crate::{aabb::Aabb, body::Body};
+
+use super::BroadPhase;
+
+// Sorts the bodies' boxes by their left edge and sweeps along x, only testing boxes whose x
+// ranges overlap. Bodies barely change order between updates, so the order is kept and fixed
+// up with an insertion sort, which is close to linear for a nearly sorted list. Works best
+// when the bodies are spread out horizontally.
+pub struct SweepAndPrune {
+    boxes: Vec<Aabb>,
+    // Body indices sorted by boxes[i].min.x
+    order: Vec<usize>,
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        SweepAndPrune {
+            boxes: vec![],
+            order: vec![],
+        }
+    }
+}
+
+impl BroadPhase for SweepAndPrune {
+    fn update(&mut self, bodies: &[Body]) {
+        // Bodies were removed without telling it, the indices have moved so start over
+        if bodies.len() < self.boxes.len() {
+            self.order.clear();
+        }
+
+        self.boxes = bodies.iter().map(Body::aabb).collect();
+        self.order.extend(self.order.len()..bodies.len());
+
+        for i in 1..self.order.len() {
+            let mut j = i;
+            while j > 0 && self.boxes[self.order[j - 1]].min.x > self.boxes[self.order[j]].min.x {
+                self.order.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+    }
+
+    fn remove(&mut self, index: usize) {
+        self.boxes.swap_remove(index);
+        let moved = self.boxes.len();
+        self.order.retain(|&other| other != index);
+        for other in &mut self.order {
+            if *other == moved {
+                *other = index;
+            }
+        }
+    }
+
+    fn pairs(&self, bodies: &[Body]) -> Vec<(usize, usize)> {
+        let mut pairs = vec![];
+        for (k, &i) in self.order.iter().enumerate() {
+            let a = &self.boxes[i];
+            // Everything after i starts further right, stop at the first that starts past it
+            for &j in &self.order[k + 1..] {
+                let b = &self.boxes[j];
+                if b.min.x > a.max.x {
+                    break;
+                }
+                if a.overlaps(b) && !(bodies[i].is_static && bodies[j].is_static) {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn query_aabb(&self, aabb: &Aabb, _bodies: &[Body]) -> Vec<usize> {
+        (self.order.iter())
+            .take_while(|&&index| self.boxes[index].min.x <= aabb.max.x)
+            .filter(|&&index| self.boxes[index].overlaps(aabb))
+            .copied()
+            .collect()
+    }
+}
+
+impl Default for SweepAndPrune {
+    fn default() -> Self {
+        SweepAndPrune::new()
+    }
+}
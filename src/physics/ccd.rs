@@ -0,0 +1,65 @@
+use super::{
+    body::Body,
+    collision::closest_points,
+    constants::{LINEAR_SLOP, MAX_TOI_ITERATIONS},
+    real::Real,
+    shape::Shape,
+    vec2::Vec2,
+};
+
+// Fraction of the step, between 0 and 1, at which body first comes within LINEAR_SLOP of
+// other while moving from start_pos and start_rotation to where it is now. Other is assumed
+// not to move. None if they never get that close, or were already that close at the start,
+// in which case the regular contacts handle it.
+//
+// Found by conservative advancement: nothing on the body moves faster than its linear motion
+// plus its angular motion at its furthest point, so it can safely be moved along by the
+// distance between them divided by that bound, then measured again.
+pub fn time_of_impact(
+    body: &Body,
+    start_pos: Vec2,
+    start_rotation: Real,
+    other: &Body,
+) -> Option<Real> {
+    let end_pos = body.pos;
+    let end_rotation = body.rotation;
+    let bound = (end_pos - start_pos).magnitude()
+        + (end_rotation - start_rotation).abs() * max_extent(&body.shape);
+    let target = LINEAR_SLOP * 0.5;
+
+    let mut moving = body.clone();
+    let mut t = 0.;
+    for iteration in 0..MAX_TOI_ITERATIONS {
+        moving.pos = start_pos + (end_pos - start_pos) * t;
+        moving.rotation = start_rotation + (end_rotation - start_rotation) * t;
+
+        let distance = match closest_points(other, &moving) {
+            Some((distance, _, _)) => distance,
+            None => 0.,
+        };
+        if distance < LINEAR_SLOP {
+            return if iteration == 0 { None } else { Some(t) };
+        }
+        if bound == 0. {
+            return None;
+        }
+
+        t += (distance - target) / bound;
+        if t >= 1. {
+            return None;
+        }
+    }
+    Some(t)
+}
+
+// Distance from the body's position to the furthest point of its shape
+fn max_extent(shape: &Shape) -> Real {
+    match shape {
+        Shape::Circle(radius) => *radius,
+        Shape::Polygon(vertices) => vertices
+            .iter()
+            .map(|vertex| vertex.magnitude())
+            .fold(0., Real::max),
+        Shape::Box(width, height) => (width * width + height * height).sqrt() / 2.,
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    body::Body, constraint::RopeJoint, handle::BodyHandle, real::Real, shape::Shape, vec2::Vec2,
+    world::World,
+};
+
+// Describes a rope or chain of small links laid out in a straight line from start to end.
+// Neighbouring links are tied centre to centre with rope joints, like the particles of a
+// Verlet rope, so the chain can go slack but won't stretch under load. Each end can optionally
+// be tied to an existing body.
+pub struct Chain {
+    pub start: Vec2,
+    pub end: Vec2,
+    pub links: usize,
+    // Circle or Box. Boxes start out with their width along the chain.
+    pub link_shape: Shape,
+    pub link_mass: Real,
+    pub start_body: Option<BodyHandle>,
+    pub end_body: Option<BodyHandle>,
+}
+
+impl Chain {
+    pub fn new(start: Vec2, end: Vec2, links: usize, link_shape: Shape) -> Self {
+        Chain {
+            start,
+            end,
+            links,
+            link_shape,
+            link_mass: 0.2,
+            start_body: None,
+            end_body: None,
+        }
+    }
+
+    // Adds the links and joints to the world, returning the handle of every body and the index
+    // of every joint created so they can be rendered or broken later. Returns None without
+    // adding anything if the links are polygons or an end body has been removed from the world.
+    pub fn build(&self, world: &mut World) -> Option<(Vec<BodyHandle>, Vec<usize>)> {
+        if let Shape::Polygon(_) = self.link_shape {
+            return None;
+        }
+        // Where the chain's ends are on the end bodies
+        let start_anchor = match self.start_body {
+            Some(body) => Some((body, world.body(body)?.world_to_local(self.start))),
+            None => None,
+        };
+        let end_anchor = match self.end_body {
+            Some(body) => Some((body, world.body(body)?.world_to_local(self.end))),
+            None => None,
+        };
+
+        let direction = (self.end - self.start).unit_vector();
+        let rotation = direction.y.atan2(direction.x);
+        let spacing = (self.end - self.start).magnitude() / self.links as Real;
+        let centre = Vec2::new(0., 0.);
+
+        let mut links = vec![];
+        for i in 0..self.links {
+            let pos = self.start + direction * (spacing * (i as Real + 0.5));
+            let mut link = Body::new(self.link_shape.clone(), pos.x, pos.y, self.link_mass, None);
+            link.rotation = rotation;
+            links.push(world.add_body(link));
+        }
+
+        // Joints are added from the start of the chain to the end. The solver visits them in
+        // this order, and a long hanging chain blows up if its support is solved last.
+        let mut joints = vec![];
+        if let (Some((start_body, anchor)), Some(&first)) = (start_anchor, links.first()) {
+            let joint = RopeJoint::new(start_body, first, anchor, centre, spacing / 2.);
+            joints.push(world.add_joint(joint));
+        }
+        for pair in links.windows(2) {
+            let joint = RopeJoint::new(pair[0], pair[1], centre, centre, spacing);
+            joints.push(world.add_joint(joint));
+        }
+        if let (Some((end_body, anchor)), Some(&last)) = (end_anchor, links.last()) {
+            let joint = RopeJoint::new(last, end_body, centre, anchor, spacing / 2.);
+            joints.push(world.add_joint(joint));
+        }
+
+        Some((links, joints))
+    }
+}
@@ -1,27 +1,30 @@
-use super::{body::Body, contact::Contact, shape::Shape, vec2::Vec2};
+use super::{
+    body::Body,
+    constants::LINEAR_SLOP,
+    contact::{Contact, Manifold},
+    real::Real,
+    shape::Shape,
+    vec2::Vec2,
+};
 use core::panic;
 
-pub fn is_colliding<'a>(a: &'a mut Body, b: &'a mut Body) -> Option<Contact<'a>> {
-    match a.shape {
+// The normal of every contact always points from a to b
+pub fn is_colliding(a: &Body, b: &Body) -> Option<Manifold> {
+    let contact = match a.shape {
         Shape::Circle(_) => match b.shape {
             Shape::Circle(_) => is_collidng_circle_circle(a, b),
             Shape::Polygon(_) => is_collidng_circle_polygon(a, b),
             Shape::Box(_, _) => is_collidng_circle_polygon(a, b),
         },
-        Shape::Polygon(_) => match b.shape {
-            Shape::Circle(_) => is_collidng_circle_polygon(b, a),
-            Shape::Polygon(_) => is_collidng_polygon_polygon(a, b),
-            Shape::Box(_, _) => is_collidng_polygon_polygon(b, a),
-        },
-        Shape::Box(_, _) => match b.shape {
-            Shape::Circle(_) => is_collidng_circle_polygon(b, a),
-            Shape::Polygon(_) => is_collidng_polygon_polygon(a, b),
-            Shape::Box(_, _) => is_collidng_polygon_polygon(b, a),
+        Shape::Polygon(_) | Shape::Box(_, _) => match b.shape {
+            Shape::Circle(_) => is_collidng_circle_polygon(b, a).map(Contact::flipped),
+            Shape::Polygon(_) | Shape::Box(_, _) => return is_collidng_polygon_polygon(a, b),
         },
-    }
+    };
+    contact.map(Manifold::from)
 }
 
-pub fn is_collidng_circle_circle<'a>(a: &'a mut Body, b: &'a mut Body) -> Option<Contact<'a>> {
+pub fn is_collidng_circle_circle(a: &Body, b: &Body) -> Option<Contact> {
     let a_radius;
     let b_radius;
     match a.shape {
@@ -46,13 +49,13 @@ pub fn is_collidng_circle_circle<'a>(a: &'a mut Body, b: &'a mut Body) -> Option
         let start = b.pos - normal * b_radius;
         let end = a.pos + normal * a_radius;
         let depth = (end - start).magnitude();
-        Some(Contact::new(a, b, start, end, normal, depth))
+        Some(Contact::new(start, end, normal, depth))
     } else {
         None
     }
 }
 
-pub fn is_collidng_polygon_polygon<'a>(a: &'a mut Body, b: &'a mut Body) -> Option<Contact<'a>> {
+pub fn is_collidng_polygon_polygon(a: &Body, b: &Body) -> Option<Manifold> {
     if let Shape::Circle(_) = a.shape {
         panic!("Wrong collision function called")
     }
@@ -60,45 +63,58 @@ pub fn is_collidng_polygon_polygon<'a>(a: &'a mut Body, b: &'a mut Body) -> Opti
         panic!("Wrong collision function called")
     }
 
-    let (ab_seperation, a_axis, a_point) = find_min_separation(a, b);
-    let (ba_seperation, b_axis, b_point) = find_min_separation(b, a);
+    let (ab_seperation, a_edge, a_point) = find_min_separation(a, b);
+    let (ba_seperation, b_edge, b_point) = find_min_separation(b, a);
 
     if ab_seperation >= 0. || ba_seperation >= 0. {
         return None;
     }
 
-    if ab_seperation > ba_seperation {
-        let depth = -ab_seperation;
-        let normal = a_axis.normal();
-        let start = a_point;
-        let end = a_point + normal * depth;
-        Some(Contact::new(a, b, start, end, normal, depth))
+    // The edge that separates the bodies the most is the reference, the contact points come
+    // from clipping the other body's edge against it. a is preferred when they're about the
+    // same, otherwise equal boxes swap reference every update and lose their warm start.
+    if ab_seperation + 0.1 * LINEAR_SLOP >= ba_seperation {
+        let manifold = clip_edges(a, b, a_edge).unwrap_or_else(|| {
+            let depth = -ab_seperation;
+            let normal = a.shape.edge_at(a_edge, a.rotation, a.pos).normal();
+            let start = a_point;
+            let end = a_point + normal * depth;
+            Manifold::from(Contact::new(start, end, normal, depth))
+        });
+        Some(manifold)
     } else {
-        let depth = -ba_seperation;
-        let normal = -b_axis.normal();
-        let start = b_point - normal * depth;
-        let end = b_point;
-        Some(Contact::new(a, b, start, end, normal, depth))
+        let manifold = clip_edges(b, a, b_edge).unwrap_or_else(|| {
+            let depth = -ba_seperation;
+            let normal = b.shape.edge_at(b_edge, b.rotation, b.pos).normal();
+            let start = b_point;
+            let end = b_point + normal * depth;
+            Manifold::from(Contact::new(start, end, normal, depth))
+        });
+        // Keep the ids apart from the ones where a is the reference
+        let mut manifold = manifold.flipped();
+        for contact in &mut manifold.contacts {
+            contact.id |= 1 << 31;
+        }
+        Some(manifold)
     }
 }
 
-fn find_min_separation<'a>(a: &'a Body, b: &'a Body) -> (f32, Vec2, Vec2) {
+// Returns the separation, the edge of a it was measured against, and the deepest vertex of b
+fn find_min_separation(a: &Body, b: &Body) -> (Real, usize, Vec2) {
     let a_vertices = a.shape.get_world_verticies(a.rotation, a.pos);
     let b_vertices = b.shape.get_world_verticies(b.rotation, b.pos);
 
-    let mut separation = f32::MIN;
-    let mut axis = Vec2::new(0., 0.);
+    let mut separation = Real::MIN;
+    let mut edge = 0;
     let mut point = Vec2::new(0., 0.);
 
-    for i in 0..a_vertices.len() {
-        let va = a_vertices[i];
+    for (i, &va) in a_vertices.iter().enumerate() {
         let normal = a.shape.edge_at(i, a.rotation, a.pos).normal();
 
-        let mut min_sep = f32::MAX;
+        let mut min_sep = Real::MAX;
         let mut min_vertex = Vec2::new(0., 0.);
 
-        for j in 0..b_vertices.len() {
-            let vb = b_vertices[j];
+        for &vb in &b_vertices {
             let proj = (vb - va).dot(normal);
             if proj < min_sep {
                 min_sep = proj;
@@ -107,17 +123,83 @@ fn find_min_separation<'a>(a: &'a Body, b: &'a Body) -> (f32, Vec2, Vec2) {
         }
         if min_sep > separation {
             separation = min_sep;
-            axis = a.shape.edge_at(i, a.rotation, a.pos);
+            edge = i;
             point = min_vertex;
         }
     }
-    (separation, axis, point)
+    (separation, edge, point)
+}
+
+// Clips the incident edge of b, the one facing reference edge of a the most, to the sides of
+// the reference edge. Whatever is left behind the reference edge are the contact points, with
+// the normal pointing from a to b. Returns None if the edges don't overlap at all.
+fn clip_edges(a: &Body, b: &Body, reference: usize) -> Option<Manifold> {
+    let a_vertices = a.shape.get_world_verticies(a.rotation, a.pos);
+    let b_vertices = b.shape.get_world_verticies(b.rotation, b.pos);
+
+    let v1 = a_vertices[reference];
+    let v2 = a_vertices[(reference + 1) % a_vertices.len()];
+    let normal = (v2 - v1).normal();
+    let tangent = (v2 - v1).unit_vector();
+
+    let incident = (0..b_vertices.len())
+        .min_by(|&i, &j| {
+            let normal_i = b.shape.edge_at(i, b.rotation, b.pos).normal();
+            let normal_j = b.shape.edge_at(j, b.rotation, b.pos).normal();
+            normal_i.dot(normal).total_cmp(&normal_j.dot(normal))
+        })
+        .unwrap();
+    let segment = [
+        b_vertices[incident],
+        b_vertices[(incident + 1) % b_vertices.len()],
+    ];
+
+    let segment = clip_segment(segment, -tangent, -tangent.dot(v1))?;
+    let segment = clip_segment(segment, tangent, tangent.dot(v2))?;
+
+    let contacts: Vec<Contact> = segment
+        .iter()
+        .enumerate()
+        .filter_map(|(k, &point)| {
+            let separation = (point - v1).dot(normal);
+            // Points just outside are kept too, so a box resting slightly tilted still gets both
+            if separation > LINEAR_SLOP {
+                return None;
+            }
+            let mut contact = Contact::new(point, point - normal * separation, normal, -separation);
+            contact.id = (reference * 256 + incident) as u32 * 2 + k as u32;
+            Some(contact)
+        })
+        .collect();
+
+    if contacts.is_empty() {
+        None
+    } else {
+        Some(Manifold::new(contacts))
+    }
+}
+
+// Keeps the part of the segment where normal.dot(point) <= offset
+fn clip_segment(segment: [Vec2; 2], normal: Vec2, offset: Real) -> Option<[Vec2; 2]> {
+    let distance_0 = normal.dot(segment[0]) - offset;
+    let distance_1 = normal.dot(segment[1]) - offset;
+
+    match (distance_0 <= 0., distance_1 <= 0.) {
+        (true, true) => Some(segment),
+        (false, false) => None,
+        _ => {
+            let t = distance_0 / (distance_0 - distance_1);
+            let crossing = segment[0] + (segment[1] - segment[0]) * t;
+            if distance_0 <= 0. {
+                Some([segment[0], crossing])
+            } else {
+                Some([crossing, segment[1]])
+            }
+        }
+    }
 }
 
-pub fn is_collidng_circle_polygon<'a>(
-    circle: &'a mut Body,
-    polygon: &'a mut Body,
-) -> Option<Contact<'a>> {
+pub fn is_collidng_circle_polygon(circle: &Body, polygon: &Body) -> Option<Contact> {
     let radius;
     match circle.shape {
         Shape::Circle(r) => match polygon.shape {
@@ -135,7 +217,7 @@ pub fn is_collidng_circle_polygon<'a>(
     let mut is_outside = false;
     let mut min_curr_vertex = Vec2::new(0., 0.);
     let mut min_next_vertex = Vec2::new(0., 0.);
-    let mut distance_circle_edge = f32::MIN;
+    let mut distance_circle_edge = Real::MIN;
 
     for i in 0..verticies.len() {
         let current_vertex = i;
@@ -180,12 +262,12 @@ pub fn is_collidng_circle_polygon<'a>(
                 return None;
             }
 
-            let start = circle.pos - ac.unit_vector() * radius;
-            let end = a;
             let normal = -ac.unit_vector();
+            let start = a;
+            let end = circle.pos + normal * radius;
             let depth = (end - start).magnitude();
 
-            return Some(Contact::new(circle, polygon, start, end, normal, depth));
+            return Some(Contact::new(start, end, normal, depth));
         }
 
         // Circle is in region B
@@ -194,12 +276,12 @@ pub fn is_collidng_circle_polygon<'a>(
                 return None;
             }
 
-            let start = circle.pos - bc.unit_vector() * radius;
-            let end = b;
             let normal = -bc.unit_vector();
+            let start = b;
+            let end = circle.pos + normal * radius;
             let depth = (end - start).magnitude();
 
-            return Some(Contact::new(circle, polygon, start, end, normal, depth));
+            return Some(Contact::new(start, end, normal, depth));
         }
 
         // Circle is in region C
@@ -209,17 +291,103 @@ pub fn is_collidng_circle_polygon<'a>(
 
         let depth = radius - distance_circle_edge;
         let normal = -ab.normal();
-        let start = circle.pos - normal * radius;
-        let end = start + normal * depth;
+        let end = circle.pos + normal * radius;
+        let start = end - normal * depth;
 
-        return Some(Contact::new(circle, polygon, start, end, normal, depth));
+        return Some(Contact::new(start, end, normal, depth));
     }
 
     // If center of circle is inside the polygon
     let depth = radius - distance_circle_edge;
     let normal = -ab.normal();
-    let start = circle.pos - normal * radius;
-    let end = start + normal * depth;
+    let end = circle.pos + normal * radius;
+    let start = end - normal * depth;
 
-    Some(Contact::new(circle, polygon, start, end, normal, depth))
+    Some(Contact::new(start, end, normal, depth))
+}
+
+// Distance between two bodies that aren't touching, with the closest point on each. None if
+// they overlap.
+pub fn closest_points(a: &Body, b: &Body) -> Option<(Real, Vec2, Vec2)> {
+    match (&a.shape, &b.shape) {
+        (Shape::Circle(a_radius), Shape::Circle(b_radius)) => {
+            let ab = b.pos - a.pos;
+            let distance = ab.magnitude() - a_radius - b_radius;
+            if distance <= 0. {
+                return None;
+            }
+            let normal = ab.unit_vector();
+            Some((
+                distance,
+                a.pos + normal * *a_radius,
+                b.pos - normal * *b_radius,
+            ))
+        }
+        (Shape::Circle(_), _) => {
+            closest_points(b, a).map(|(distance, on_b, on_a)| (distance, on_a, on_b))
+        }
+        (_, Shape::Circle(radius)) => {
+            if a.contains_point(b.pos) {
+                return None;
+            }
+            let vertices = a.shape.get_world_verticies(a.rotation, a.pos);
+            let on_a = (0..vertices.len())
+                .map(|i| {
+                    let next = vertices[(i + 1) % vertices.len()];
+                    closest_point_on_segment(b.pos, vertices[i], next)
+                })
+                .min_by(|p, q| {
+                    let p = (b.pos - *p).magnitude_squared();
+                    let q = (b.pos - *q).magnitude_squared();
+                    p.total_cmp(&q)
+                })
+                .unwrap();
+            let to_centre = b.pos - on_a;
+            let distance = to_centre.magnitude() - radius;
+            if distance <= 0. {
+                return None;
+            }
+            Some((distance, on_a, b.pos - to_centre.unit_vector() * *radius))
+        }
+        _ => {
+            if is_collidng_polygon_polygon(a, b).is_some() {
+                return None;
+            }
+            // Two convex polygons that don't overlap are closest at a vertex of one of them
+            let a_vertices = a.shape.get_world_verticies(a.rotation, a.pos);
+            let b_vertices = b.shape.get_world_verticies(b.rotation, b.pos);
+            let mut closest = (Real::MAX, Vec2::new(0., 0.), Vec2::new(0., 0.));
+            for (i, &start) in a_vertices.iter().enumerate() {
+                let end = a_vertices[(i + 1) % a_vertices.len()];
+                for &vertex in &b_vertices {
+                    let on_a = closest_point_on_segment(vertex, start, end);
+                    let distance = (vertex - on_a).magnitude();
+                    if distance < closest.0 {
+                        closest = (distance, on_a, vertex);
+                    }
+                }
+            }
+            for (i, &start) in b_vertices.iter().enumerate() {
+                let end = b_vertices[(i + 1) % b_vertices.len()];
+                for &vertex in &a_vertices {
+                    let on_b = closest_point_on_segment(vertex, start, end);
+                    let distance = (vertex - on_b).magnitude();
+                    if distance < closest.0 {
+                        closest = (distance, vertex, on_b);
+                    }
+                }
+            }
+            Some(closest)
+        }
+    }
+}
+
+fn closest_point_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let edge = end - start;
+    let length_squared = edge.magnitude_squared();
+    if length_squared == 0. {
+        return start;
+    }
+    let t = ((point - start).dot(edge) / length_squared).clamp(0., 1.);
+    start + edge * t
 }
@@ -1,6 +1,40 @@
+use crate::real::{consts::PI, Real};
+
 pub const FPS: i32 = 60;
 pub const MILLISECS_PER_FRAME: i32 = 1000 / FPS;
-pub const PIXELS_PER_METER: f32 = 200.;
+pub const PIXELS_PER_METER: Real = 200.;
 
 pub const WIDTH: u32 = 1200;
 pub const HEIGHT: u32 = 800;
+
+// Constraint solver. Lengths are in pixels, so these are scaled from the usual metre values.
+// The iteration counts are the defaults for a new World.
+pub const VELOCITY_ITERATIONS: usize = 8;
+pub const POSITION_ITERATIONS: usize = 3;
+pub const LINEAR_SLOP: Real = 1.;
+pub const MAX_LINEAR_CORRECTION: Real = 40.;
+pub const ANGULAR_SLOP: Real = 2. / 180. * PI;
+pub const MAX_ANGULAR_CORRECTION: Real = 8. / 180. * PI;
+
+// Contact solver
+pub const BAUMGARTE: Real = 0.2;
+pub const VELOCITY_THRESHOLD: Real = 200.;
+
+// Broadphase. Boxes are grown by the margin so bodies that barely move keep their place.
+pub const AABB_MARGIN: Real = 10.;
+
+// Continuous collision for bullets. Sub-steps are how many impacts a bullet can go through in
+// one update, iterations are the conservative advancement steps to find each one.
+pub const MAX_SUB_STEPS: usize = 8;
+pub const MAX_TOI_ITERATIONS: usize = 20;
+
+// Sleeping. A body is still while it's under both tolerances, and an island of touching or
+// jointed bodies goes to sleep once all of them have been still for TIME_TO_SLEEP seconds.
+pub const TIME_TO_SLEEP: Real = 0.5;
+pub const LINEAR_SLEEP_TOLERANCE: Real = 2.;
+pub const ANGULAR_SLEEP_TOLERANCE: Real = 2. / 180. * PI;
+
+// Fixed timestep. World::step advances in steps of TIME_STEP seconds and takes at most
+// MAX_STEPS of them per call, so a slow frame can't snowball into slower and slower frames.
+pub const TIME_STEP: Real = 1. / FPS as Real;
+pub const MAX_STEPS: usize = 5;
@@ -0,0 +1,301 @@
+mod distance;
+mod gear;
+mod mouse;
+mod prismatic;
+mod pulley;
+mod revolute;
+mod rope;
+mod weld;
+mod wheel;
+
+pub use distance::DistanceJoint;
+pub use gear::GearJoint;
+pub use mouse::MouseJoint;
+pub use prismatic::PrismaticJoint;
+pub use pulley::PulleyJoint;
+pub use revolute::RevoluteJoint;
+pub use rope::RopeJoint;
+pub use weld::WeldJoint;
+pub use wheel::WheelJoint;
+
+use crate::{
+    body::Body,
+    handle::BodyHandle,
+    real::{consts::PI, Real},
+    vec2::Vec2,
+};
+
+// Joints are solved with impulses in three phases every update: init_velocity once before
+// the velocity iterations, solve_velocity on every velocity iteration, then solve_position
+// after the bodies have moved to remove any drift that the velocity pass left behind.
+pub enum Joint {
+    Distance(DistanceJoint),
+    Revolute(RevoluteJoint),
+    Prismatic(PrismaticJoint),
+    Weld(WeldJoint),
+    Mouse(MouseJoint),
+    Pulley(PulleyJoint),
+    Gear(GearJoint),
+    Wheel(WheelJoint),
+    Rope(RopeJoint),
+}
+
+impl Joint {
+    // The bodies the joint connects. They're looked up when the joint is added to the world,
+    // changing them afterwards has no effect.
+    pub fn bodies(&self) -> Vec<BodyHandle> {
+        match self {
+            Joint::Distance(joint) => vec![joint.body_a, joint.body_b],
+            Joint::Revolute(joint) => vec![joint.body_a, joint.body_b],
+            Joint::Prismatic(joint) => vec![joint.body_a, joint.body_b],
+            Joint::Weld(joint) => vec![joint.body_a, joint.body_b],
+            Joint::Mouse(joint) => vec![joint.body],
+            Joint::Pulley(joint) => vec![joint.body_a, joint.body_b],
+            Joint::Gear(joint) => joint.bodies().to_vec(),
+            Joint::Wheel(joint) => vec![joint.body_a, joint.body_b],
+            Joint::Rope(joint) => vec![joint.body_a, joint.body_b],
+        }
+    }
+
+    // Where the bodies are in world.bodies, in the same order as bodies()
+    pub(crate) fn indices(&self) -> Vec<usize> {
+        match self {
+            Joint::Distance(joint) => vec![joint.index_a, joint.index_b],
+            Joint::Revolute(joint) => vec![joint.index_a, joint.index_b],
+            Joint::Prismatic(joint) => vec![joint.index_a, joint.index_b],
+            Joint::Weld(joint) => vec![joint.index_a, joint.index_b],
+            Joint::Mouse(joint) => vec![joint.index],
+            Joint::Pulley(joint) => vec![joint.index_a, joint.index_b],
+            Joint::Gear(joint) => joint.indices().to_vec(),
+            Joint::Wheel(joint) => vec![joint.index_a, joint.index_b],
+            Joint::Rope(joint) => vec![joint.index_a, joint.index_b],
+        }
+    }
+
+    // For the world to set the indices when the joint is added, and fix them up when a body
+    // is moved
+    pub(crate) fn indices_mut(&mut self) -> Vec<&mut usize> {
+        match self {
+            Joint::Distance(joint) => vec![&mut joint.index_a, &mut joint.index_b],
+            Joint::Revolute(joint) => vec![&mut joint.index_a, &mut joint.index_b],
+            Joint::Prismatic(joint) => vec![&mut joint.index_a, &mut joint.index_b],
+            Joint::Weld(joint) => vec![&mut joint.index_a, &mut joint.index_b],
+            Joint::Mouse(joint) => vec![&mut joint.index],
+            Joint::Pulley(joint) => vec![&mut joint.index_a, &mut joint.index_b],
+            Joint::Gear(joint) => joint.indices_mut().into(),
+            Joint::Wheel(joint) => vec![&mut joint.index_a, &mut joint.index_b],
+            Joint::Rope(joint) => vec![&mut joint.index_a, &mut joint.index_b],
+        }
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], dt: Real) {
+        match self {
+            Joint::Distance(joint) => joint.init_velocity(bodies, dt),
+            Joint::Revolute(joint) => joint.init_velocity(bodies, dt),
+            Joint::Prismatic(joint) => joint.init_velocity(bodies, dt),
+            Joint::Weld(joint) => joint.init_velocity(bodies, dt),
+            Joint::Mouse(joint) => joint.init_velocity(bodies, dt),
+            Joint::Pulley(joint) => joint.init_velocity(bodies, dt),
+            Joint::Gear(joint) => joint.init_velocity(bodies, dt),
+            Joint::Wheel(joint) => joint.init_velocity(bodies, dt),
+            Joint::Rope(joint) => joint.init_velocity(bodies, dt),
+        }
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        match self {
+            Joint::Distance(joint) => joint.solve_velocity(bodies),
+            Joint::Revolute(joint) => joint.solve_velocity(bodies),
+            Joint::Prismatic(joint) => joint.solve_velocity(bodies),
+            Joint::Weld(joint) => joint.solve_velocity(bodies),
+            Joint::Mouse(joint) => joint.solve_velocity(bodies),
+            Joint::Pulley(joint) => joint.solve_velocity(bodies),
+            Joint::Gear(joint) => joint.solve_velocity(bodies),
+            Joint::Wheel(joint) => joint.solve_velocity(bodies),
+            Joint::Rope(joint) => joint.solve_velocity(bodies),
+        }
+    }
+
+    // Returns true once the joint's position error is within tolerance
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        match self {
+            Joint::Distance(joint) => joint.solve_position(bodies),
+            Joint::Revolute(joint) => joint.solve_position(bodies),
+            Joint::Prismatic(joint) => joint.solve_position(bodies),
+            Joint::Weld(joint) => joint.solve_position(bodies),
+            Joint::Mouse(joint) => joint.solve_position(bodies),
+            Joint::Pulley(joint) => joint.solve_position(bodies),
+            Joint::Gear(joint) => joint.solve_position(bodies),
+            Joint::Wheel(joint) => joint.solve_position(bodies),
+            Joint::Rope(joint) => joint.solve_position(bodies),
+        }
+    }
+    // Force and torque the joint applied over the last update. Two body joints report what
+    // they applied to their second body.
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        match self {
+            Joint::Distance(joint) => joint.reaction_force(dt),
+            Joint::Revolute(joint) => joint.reaction_force(dt),
+            Joint::Prismatic(joint) => joint.reaction_force(dt),
+            Joint::Weld(joint) => joint.reaction_force(dt),
+            Joint::Mouse(joint) => joint.reaction_force(dt),
+            Joint::Pulley(joint) => joint.reaction_force(dt),
+            Joint::Gear(joint) => joint.reaction_force(dt),
+            Joint::Wheel(joint) => joint.reaction_force(dt),
+            Joint::Rope(joint) => joint.reaction_force(dt),
+        }
+    }
+
+    pub fn reaction_torque(&self, dt: Real) -> Real {
+        match self {
+            Joint::Revolute(joint) => joint.reaction_torque(dt),
+            Joint::Prismatic(joint) => joint.reaction_torque(dt),
+            Joint::Weld(joint) => joint.reaction_torque(dt),
+            Joint::Gear(joint) => joint.reaction_torque(dt),
+            Joint::Wheel(joint) => joint.reaction_torque(dt),
+            Joint::Distance(_) | Joint::Mouse(_) | Joint::Pulley(_) | Joint::Rope(_) => 0.,
+        }
+    }
+
+    // Every joint can be given a break_force and break_torque. The world removes it once the
+    // reaction needed to hold it together goes over either of them.
+    pub(crate) fn is_broken(&self, dt: Real) -> bool {
+        let (break_force, break_torque) = match self {
+            Joint::Distance(joint) => (joint.break_force, joint.break_torque),
+            Joint::Revolute(joint) => (joint.break_force, joint.break_torque),
+            Joint::Prismatic(joint) => (joint.break_force, joint.break_torque),
+            Joint::Weld(joint) => (joint.break_force, joint.break_torque),
+            Joint::Mouse(joint) => (joint.break_force, joint.break_torque),
+            Joint::Pulley(joint) => (joint.break_force, joint.break_torque),
+            Joint::Gear(joint) => (joint.break_force, joint.break_torque),
+            Joint::Wheel(joint) => (joint.break_force, joint.break_torque),
+            Joint::Rope(joint) => (joint.break_force, joint.break_torque),
+        };
+        break_force.is_some_and(|max| self.reaction_force(dt).magnitude() > max)
+            || break_torque.is_some_and(|max| self.reaction_torque(dt).abs() > max)
+    }
+}
+
+// Drives a joint towards a target speed, in radians per second for rotating joints. max_force
+// is the largest torque (or force, for sliding joints) the motor may apply.
+#[derive(Clone, Copy, Debug)]
+pub struct Motor {
+    pub speed: Real,
+    pub max_force: Real,
+}
+
+impl Motor {
+    pub fn new(speed: Real, max_force: Real) -> Self {
+        Motor { speed, max_force }
+    }
+}
+
+// Softens a constraint into a damped spring. frequency is in hertz and a damping_ratio of 1
+// is critically damped.
+#[derive(Clone, Copy, Debug)]
+pub struct Spring {
+    pub frequency: Real,
+    pub damping_ratio: Real,
+}
+
+impl Spring {
+    pub fn new(frequency: Real, damping_ratio: Real) -> Self {
+        Spring {
+            frequency,
+            damping_ratio,
+        }
+    }
+
+    // Returns (gamma, beta) for a constraint with the given effective mass. gamma softens the
+    // constraint mass and beta scales the position error into a velocity bias.
+    pub(crate) fn coefficients(&self, mass: Real, dt: Real) -> (Real, Real) {
+        let omega = 2. * PI * self.frequency;
+        let d = 2. * mass * self.damping_ratio * omega;
+        let k = mass * omega * omega;
+
+        let gamma = dt * (d + dt * k);
+        let gamma = if gamma != 0. { 1. / gamma } else { 0. };
+        (gamma, dt * k * gamma)
+    }
+}
+
+// Counter-clockwise perpendicular, unlike Vec2::normal this keeps the length
+fn perpendicular(v: Vec2) -> Vec2 {
+    Vec2::new(-v.y, v.x)
+}
+
+// Effective mass of two anchors pulled apart along the unit vector u
+pub(crate) fn axial_mass(a: &Body, b: &Body, ra: Vec2, rb: Vec2, u: Vec2) -> Real {
+    let cr_a = ra.cross(u);
+    let cr_b = rb.cross(u);
+    let inv_mass =
+        a.inv_mass + a.inv_inertia * cr_a * cr_a + b.inv_mass + b.inv_inertia * cr_b * cr_b;
+    if inv_mass != 0. {
+        1. / inv_mass
+    } else {
+        0.
+    }
+}
+
+// Applies a linear impulse p along with angular impulses that aren't simply r x p, which is
+// how the axis constraints of the prismatic and wheel joints are expressed
+fn apply_split_impulse(a: &mut Body, b: &mut Body, p: Vec2, la: Real, lb: Real) {
+    a.vel -= p * a.inv_mass;
+    a.ang_vel -= a.inv_inertia * la;
+    b.vel += p * b.inv_mass;
+    b.ang_vel += b.inv_inertia * lb;
+}
+
+impl From<DistanceJoint> for Joint {
+    fn from(joint: DistanceJoint) -> Self {
+        Joint::Distance(joint)
+    }
+}
+
+impl From<RevoluteJoint> for Joint {
+    fn from(joint: RevoluteJoint) -> Self {
+        Joint::Revolute(joint)
+    }
+}
+
+impl From<PrismaticJoint> for Joint {
+    fn from(joint: PrismaticJoint) -> Self {
+        Joint::Prismatic(joint)
+    }
+}
+
+impl From<WeldJoint> for Joint {
+    fn from(joint: WeldJoint) -> Self {
+        Joint::Weld(joint)
+    }
+}
+
+impl From<MouseJoint> for Joint {
+    fn from(joint: MouseJoint) -> Self {
+        Joint::Mouse(joint)
+    }
+}
+
+impl From<PulleyJoint> for Joint {
+    fn from(joint: PulleyJoint) -> Self {
+        Joint::Pulley(joint)
+    }
+}
+
+impl From<GearJoint> for Joint {
+    fn from(joint: GearJoint) -> Self {
+        Joint::Gear(joint)
+    }
+}
+
+impl From<WheelJoint> for Joint {
+    fn from(joint: WheelJoint) -> Self {
+        Joint::Wheel(joint)
+    }
+}
+
+impl From<RopeJoint> for Joint {
+    fn from(joint: RopeJoint) -> Self {
+        Joint::Rope(joint)
+    }
+}
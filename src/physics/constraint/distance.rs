@@ -0,0 +1,121 @@
+use crate::{
+    body::{get_pair_mut, Body},
+    constants::{LINEAR_SLOP, MAX_LINEAR_CORRECTION},
+    handle::BodyHandle,
+    real::Real,
+    vec2::Vec2,
+};
+
+use super::axial_mass;
+
+// Keeps the anchor points of two bodies a fixed distance apart. Anchors are in each body's
+// local space, so Vec2::new(0., 0.) pins the joint to the body's centre.
+pub struct DistanceJoint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub length: Real,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    // Where the bodies are in world.bodies, set by World::add_joint
+    pub(crate) index_a: usize,
+    pub(crate) index_b: usize,
+    impulse: Real,
+    u: Vec2,
+    ra: Vec2,
+    rb: Vec2,
+    mass: Real,
+}
+
+impl DistanceJoint {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        local_anchor_a: Vec2,
+        local_anchor_b: Vec2,
+        length: Real,
+    ) -> Self {
+        DistanceJoint {
+            body_a,
+            body_b,
+            index_a: 0,
+            index_b: 0,
+            local_anchor_a,
+            local_anchor_b,
+            length: Real::max(length, LINEAR_SLOP),
+            break_force: None,
+            break_torque: None,
+            impulse: 0.,
+            u: Vec2::new(0., 0.),
+            ra: Vec2::new(0., 0.),
+            rb: Vec2::new(0., 0.),
+            mass: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        self.u * self.impulse / dt
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], _dt: Real) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        self.ra = self.local_anchor_a.rotate(a.rotation);
+        self.rb = self.local_anchor_b.rotate(b.rotation);
+        self.u = b.pos + self.rb - a.pos - self.ra;
+
+        // Anchors on top of each other give no direction to push along
+        if self.u.magnitude() > LINEAR_SLOP {
+            self.u.normalize();
+        } else {
+            self.u = Vec2::new(0., 0.);
+        }
+
+        self.mass = axial_mass(a, b, self.ra, self.rb, self.u);
+
+        // Warm start with last frame's impulse
+        let p = self.u * self.impulse;
+        a.apply_impulse(-p, self.ra);
+        b.apply_impulse(p, self.rb);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        let va = a.velocity_at(self.ra);
+        let vb = b.velocity_at(self.rb);
+        let c_dot = self.u.dot(vb - va);
+
+        let impulse = -self.mass * c_dot;
+        self.impulse += impulse;
+
+        let p = self.u * impulse;
+        a.apply_impulse(-p, self.ra);
+        b.apply_impulse(p, self.rb);
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let mut u = b.pos + rb - a.pos - ra;
+
+        let length = u.magnitude();
+        u.normalize();
+        let c = (length - self.length).clamp(-MAX_LINEAR_CORRECTION, MAX_LINEAR_CORRECTION);
+
+        // The bodies have moved since the velocity phase, so the mass is recomputed
+        let impulse = -axial_mass(a, b, ra, rb, u) * c;
+        let p = u * impulse;
+
+        a.pos -= p * a.inv_mass;
+        a.rotation -= a.inv_inertia * ra.cross(p);
+        b.pos += p * b.inv_mass;
+        b.rotation += b.inv_inertia * rb.cross(p);
+
+        c.abs() < LINEAR_SLOP
+    }
+}
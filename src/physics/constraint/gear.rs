@@ -0,0 +1,306 @@
+use crate::{body::Body, handle::BodyHandle, real::Real, vec2::Vec2, world::World};
+
+use super::Joint;
+
+// Couples two revolute or prismatic joints so that coordinate_a + ratio * coordinate_b stays
+// constant, where a coordinate is the joint's angle or translation. Both joints must stay in
+// the world, the gear only adds to them.
+//
+// Body a and b are the second bodies of the two joints, c and d are the bodies the joints
+// attach them to (often the ground).
+pub struct GearJoint {
+    pub ratio: Real,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    body_a: BodyHandle,
+    body_b: BodyHandle,
+    body_c: BodyHandle,
+    body_d: BodyHandle,
+    index_a: usize,
+    index_b: usize,
+    index_c: usize,
+    index_d: usize,
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    local_anchor_c: Vec2,
+    local_anchor_d: Vec2,
+    // None for a revolute joint, the sliding axis for a prismatic one
+    local_axis_c: Option<Vec2>,
+    local_axis_d: Option<Vec2>,
+    reference_angle_a: Real,
+    reference_angle_b: Real,
+    constant: Real,
+
+    impulse: Real,
+    jv_ac: Vec2,
+    jv_bd: Vec2,
+    jw_a: Real,
+    jw_b: Real,
+    jw_c: Real,
+    jw_d: Real,
+    mass: Real,
+}
+
+// The parts of a revolute or prismatic joint the gear needs
+struct GearSide {
+    body: BodyHandle,
+    ground: BodyHandle,
+    local_anchor: Vec2,
+    local_anchor_ground: Vec2,
+    local_axis: Option<Vec2>,
+    reference_angle: Real,
+}
+
+impl GearSide {
+    // None for any other kind of joint
+    fn from_joint(joint: &Joint) -> Option<Self> {
+        let side = match joint {
+            Joint::Revolute(joint) => GearSide {
+                body: joint.body_b,
+                ground: joint.body_a,
+                local_anchor: joint.local_anchor_b,
+                local_anchor_ground: joint.local_anchor_a,
+                local_axis: None,
+                reference_angle: joint.reference_angle,
+            },
+            Joint::Prismatic(joint) => GearSide {
+                body: joint.body_b,
+                ground: joint.body_a,
+                local_anchor: joint.local_anchor_b,
+                local_anchor_ground: joint.local_anchor_a,
+                local_axis: Some(joint.local_axis_a),
+                reference_angle: joint.reference_angle,
+            },
+            _ => return None,
+        };
+        Some(side)
+    }
+}
+
+impl GearJoint {
+    // joint_a and joint_b are indices into world.joints. Returns None unless both are revolute
+    // or prismatic joints.
+    pub fn new(world: &World, joint_a: usize, joint_b: usize, ratio: Real) -> Option<Self> {
+        let side_a = GearSide::from_joint(world.joints.get(joint_a)?)?;
+        let side_b = GearSide::from_joint(world.joints.get(joint_b)?)?;
+
+        let mut gear = GearJoint {
+            ratio,
+            break_force: None,
+            break_torque: None,
+            body_a: side_a.body,
+            body_b: side_b.body,
+            body_c: side_a.ground,
+            body_d: side_b.ground,
+            index_a: 0,
+            index_b: 0,
+            index_c: 0,
+            index_d: 0,
+            local_anchor_a: side_a.local_anchor,
+            local_anchor_b: side_b.local_anchor,
+            local_anchor_c: side_a.local_anchor_ground,
+            local_anchor_d: side_b.local_anchor_ground,
+            local_axis_c: side_a.local_axis,
+            local_axis_d: side_b.local_axis,
+            reference_angle_a: side_a.reference_angle,
+            reference_angle_b: side_b.reference_angle,
+            constant: 0.,
+            impulse: 0.,
+            jv_ac: Vec2::new(0., 0.),
+            jv_bd: Vec2::new(0., 0.),
+            jw_a: 0.,
+            jw_b: 0.,
+            jw_c: 0.,
+            jw_d: 0.,
+            mass: 0.,
+        };
+
+        let coordinate_a = coordinate(
+            world.body(gear.body_a)?,
+            world.body(gear.body_c)?,
+            gear.local_anchor_a,
+            gear.local_anchor_c,
+            gear.local_axis_c,
+            gear.reference_angle_a,
+        );
+        let coordinate_b = coordinate(
+            world.body(gear.body_b)?,
+            world.body(gear.body_d)?,
+            gear.local_anchor_b,
+            gear.local_anchor_d,
+            gear.local_axis_d,
+            gear.reference_angle_b,
+        );
+        gear.constant = coordinate_a + ratio * coordinate_b;
+        Some(gear)
+    }
+
+    pub fn bodies(&self) -> [BodyHandle; 4] {
+        [self.body_a, self.body_b, self.body_c, self.body_d]
+    }
+
+    pub(crate) fn indices(&self) -> [usize; 4] {
+        [self.index_a, self.index_b, self.index_c, self.index_d]
+    }
+
+    pub(crate) fn indices_mut(&mut self) -> [&mut usize; 4] {
+        [
+            &mut self.index_a,
+            &mut self.index_b,
+            &mut self.index_c,
+            &mut self.index_d,
+        ]
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        self.jv_ac * self.impulse / dt
+    }
+
+    pub fn reaction_torque(&self, dt: Real) -> Real {
+        self.impulse * self.jw_a / dt
+    }
+
+    // Jacobian of the gear constraint and its effective mass
+    fn prepare(&mut self, bodies: &[Body]) {
+        let (a, b) = (&bodies[self.index_a], &bodies[self.index_b]);
+        let (c, d) = (&bodies[self.index_c], &bodies[self.index_d]);
+        let mut mass = 0.;
+
+        match self.local_axis_c {
+            None => {
+                self.jv_ac = Vec2::new(0., 0.);
+                self.jw_a = 1.;
+                self.jw_c = 1.;
+                mass += a.inv_inertia + c.inv_inertia;
+            }
+            Some(axis) => {
+                let u = axis.rotate(c.rotation);
+                let rc = self.local_anchor_c.rotate(c.rotation);
+                let ra = self.local_anchor_a.rotate(a.rotation);
+                self.jv_ac = u;
+                self.jw_c = rc.cross(u);
+                self.jw_a = ra.cross(u);
+                mass += c.inv_mass
+                    + a.inv_mass
+                    + c.inv_inertia * self.jw_c * self.jw_c
+                    + a.inv_inertia * self.jw_a * self.jw_a;
+            }
+        }
+
+        match self.local_axis_d {
+            None => {
+                self.jv_bd = Vec2::new(0., 0.);
+                self.jw_b = self.ratio;
+                self.jw_d = self.ratio;
+                mass += self.ratio * self.ratio * (b.inv_inertia + d.inv_inertia);
+            }
+            Some(axis) => {
+                let u = axis.rotate(d.rotation);
+                let rd = self.local_anchor_d.rotate(d.rotation);
+                let rb = self.local_anchor_b.rotate(b.rotation);
+                self.jv_bd = u * self.ratio;
+                self.jw_d = self.ratio * rd.cross(u);
+                self.jw_b = self.ratio * rb.cross(u);
+                mass += self.ratio * self.ratio * (d.inv_mass + b.inv_mass)
+                    + d.inv_inertia * self.jw_d * self.jw_d
+                    + b.inv_inertia * self.jw_b * self.jw_b;
+            }
+        }
+
+        self.mass = if mass > 0. { 1. / mass } else { 0. };
+    }
+
+    // Up to four bodies are involved and c and d are often the same ground body, so the
+    // impulse is applied to each body in turn instead of borrowing them all at once
+    fn apply_velocity_impulse(&self, bodies: &mut [Body], impulse: Real) {
+        let a = &mut bodies[self.index_a];
+        a.vel += self.jv_ac * (a.inv_mass * impulse);
+        a.ang_vel += a.inv_inertia * impulse * self.jw_a;
+        let b = &mut bodies[self.index_b];
+        b.vel += self.jv_bd * (b.inv_mass * impulse);
+        b.ang_vel += b.inv_inertia * impulse * self.jw_b;
+        let c = &mut bodies[self.index_c];
+        c.vel -= self.jv_ac * (c.inv_mass * impulse);
+        c.ang_vel -= c.inv_inertia * impulse * self.jw_c;
+        let d = &mut bodies[self.index_d];
+        d.vel -= self.jv_bd * (d.inv_mass * impulse);
+        d.ang_vel -= d.inv_inertia * impulse * self.jw_d;
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], _dt: Real) {
+        self.prepare(bodies);
+        self.apply_velocity_impulse(bodies, self.impulse);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = (&bodies[self.index_a], &bodies[self.index_b]);
+        let (c, d) = (&bodies[self.index_c], &bodies[self.index_d]);
+
+        let c_dot = self.jv_ac.dot(a.vel - c.vel)
+            + self.jv_bd.dot(b.vel - d.vel)
+            + (self.jw_a * a.ang_vel - self.jw_c * c.ang_vel)
+            + (self.jw_b * b.ang_vel - self.jw_d * d.ang_vel);
+
+        let impulse = -self.mass * c_dot;
+        self.impulse += impulse;
+        self.apply_velocity_impulse(bodies, impulse);
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        self.prepare(bodies);
+
+        let coordinate_a = coordinate(
+            &bodies[self.index_a],
+            &bodies[self.index_c],
+            self.local_anchor_a,
+            self.local_anchor_c,
+            self.local_axis_c,
+            self.reference_angle_a,
+        );
+        let coordinate_b = coordinate(
+            &bodies[self.index_b],
+            &bodies[self.index_d],
+            self.local_anchor_b,
+            self.local_anchor_d,
+            self.local_axis_d,
+            self.reference_angle_b,
+        );
+        let c = coordinate_a + self.ratio * coordinate_b - self.constant;
+        let impulse = if self.mass > 0. { -c * self.mass } else { 0. };
+
+        let a = &mut bodies[self.index_a];
+        a.pos += self.jv_ac * (a.inv_mass * impulse);
+        a.rotation += a.inv_inertia * impulse * self.jw_a;
+        let b = &mut bodies[self.index_b];
+        b.pos += self.jv_bd * (b.inv_mass * impulse);
+        b.rotation += b.inv_inertia * impulse * self.jw_b;
+        let c = &mut bodies[self.index_c];
+        c.pos -= self.jv_ac * (c.inv_mass * impulse);
+        c.rotation -= c.inv_inertia * impulse * self.jw_c;
+        let d = &mut bodies[self.index_d];
+        d.pos -= self.jv_bd * (d.inv_mass * impulse);
+        d.rotation -= d.inv_inertia * impulse * self.jw_d;
+
+        // The coupled joints carry the position error, so the gear never holds up the solver
+        true
+    }
+}
+
+// Angle of a revolute joint, or translation of a prismatic joint, between body and ground
+fn coordinate(
+    body: &Body,
+    ground: &Body,
+    local_anchor: Vec2,
+    local_anchor_ground: Vec2,
+    local_axis: Option<Vec2>,
+    reference_angle: Real,
+) -> Real {
+    match local_axis {
+        None => body.rotation - ground.rotation - reference_angle,
+        Some(axis) => {
+            let p = ground.world_to_local(body.local_to_world(local_anchor));
+            (p - local_anchor_ground).dot(axis)
+        }
+    }
+}
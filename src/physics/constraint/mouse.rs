@@ -0,0 +1,94 @@
+use crate::{body::Body, handle::BodyHandle, mat22::Mat22, real::Real, vec2::Vec2};
+
+use super::Spring;
+
+// Pulls a point on a body towards a world target with a soft spring. Used to drag bodies
+// around with the mouse, max_force stops it from yanking heavy bodies through walls.
+pub struct MouseJoint {
+    pub body: BodyHandle,
+    pub local_anchor: Vec2,
+    pub target: Vec2,
+    pub max_force: Real,
+    pub spring: Spring,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    pub(crate) index: usize,
+    impulse: Vec2,
+    r: Vec2,
+    mass: Mat22,
+    c: Vec2,
+    gamma: Real,
+    dt: Real,
+}
+
+impl MouseJoint {
+    pub fn new(body: BodyHandle, local_anchor: Vec2, target: Vec2, max_force: Real) -> Self {
+        MouseJoint {
+            body,
+            index: 0,
+            local_anchor,
+            target,
+            max_force,
+            spring: Spring::new(5., 0.7),
+            break_force: None,
+            break_torque: None,
+            impulse: Vec2::new(0., 0.),
+            r: Vec2::new(0., 0.),
+            mass: Mat22::new(Vec2::new(0., 0.), Vec2::new(0., 0.)),
+            c: Vec2::new(0., 0.),
+            gamma: 0.,
+            dt: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        self.impulse / dt
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], dt: Real) {
+        let body = &mut bodies[self.index];
+        let (m, i) = (body.inv_mass, body.inv_inertia);
+
+        self.dt = dt;
+        let mass = if m > 0. { 1. / m } else { 0. };
+        let (gamma, beta) = self.spring.coefficients(mass, dt);
+        self.gamma = gamma;
+
+        self.r = self.local_anchor.rotate(body.rotation);
+        let r = self.r;
+        let k = Mat22::new(
+            Vec2::new(m + i * r.y * r.y + gamma, -i * r.x * r.y),
+            Vec2::new(-i * r.x * r.y, m + i * r.x * r.x + gamma),
+        );
+        self.mass = k.inverse();
+        self.c = (body.pos + r - self.target) * beta;
+
+        // Cheap angular damping so a grabbed body doesn't spin forever around the cursor
+        body.ang_vel *= 0.98;
+
+        body.apply_impulse(self.impulse, self.r);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let body = &mut bodies[self.index];
+
+        let c_dot = body.velocity_at(self.r);
+        let impulse = self.mass.mul(-(c_dot + self.c + self.impulse * self.gamma));
+
+        let old_impulse = self.impulse;
+        self.impulse += impulse;
+        let max_impulse = self.dt * self.max_force;
+        if self.impulse.magnitude_squared() > max_impulse * max_impulse {
+            self.impulse = self.impulse.unit_vector() * max_impulse;
+        }
+        let impulse = self.impulse - old_impulse;
+
+        body.apply_impulse(impulse, self.r);
+    }
+
+    // The spring handles position error itself
+    pub(crate) fn solve_position(&mut self, _bodies: &mut [Body]) -> bool {
+        true
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    body::{get_pair_mut, Body},
+    constants::{ANGULAR_SLOP, LINEAR_SLOP},
+    handle::BodyHandle,
+    mat22::Mat22,
+    mat33::{Mat33, Vec3},
+    real::Real,
+    vec2::Vec2,
+};
+
+use super::{apply_split_impulse, perpendicular, Motor};
+
+// Lets body b slide along an axis fixed in body a, with no relative rotation. The axis is in
+// body a's local space and translation is measured from the anchors lining up.
+pub struct PrismaticJoint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub local_axis_a: Vec2,
+    pub reference_angle: Real,
+    // Lower and upper translation along the axis, in pixels
+    pub limits: Option<(Real, Real)>,
+    pub motor: Option<Motor>,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    pub(crate) index_a: usize,
+    pub(crate) index_b: usize,
+    impulse: Vec2,
+    motor_impulse: Real,
+    lower_impulse: Real,
+    upper_impulse: Real,
+    axis: Vec2,
+    perp: Vec2,
+    s1: Real,
+    s2: Real,
+    a1: Real,
+    a2: Real,
+    k: Mat22,
+    axial_mass: Real,
+    translation: Real,
+    dt: Real,
+}
+
+impl PrismaticJoint {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        local_anchor_a: Vec2,
+        local_anchor_b: Vec2,
+        local_axis_a: Vec2,
+    ) -> Self {
+        PrismaticJoint {
+            body_a,
+            body_b,
+            index_a: 0,
+            index_b: 0,
+            local_anchor_a,
+            local_anchor_b,
+            local_axis_a: local_axis_a.unit_vector(),
+            reference_angle: 0.,
+            limits: None,
+            motor: None,
+            break_force: None,
+            break_torque: None,
+            impulse: Vec2::new(0., 0.),
+            motor_impulse: 0.,
+            lower_impulse: 0.,
+            upper_impulse: 0.,
+            axis: Vec2::new(0., 0.),
+            perp: Vec2::new(0., 0.),
+            s1: 0.,
+            s2: 0.,
+            a1: 0.,
+            a2: 0.,
+            k: Mat22::new(Vec2::new(0., 0.), Vec2::new(0., 0.)),
+            axial_mass: 0.,
+            translation: 0.,
+            dt: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        (self.perp * self.impulse.x
+            + self.axis * (self.motor_impulse + self.lower_impulse - self.upper_impulse))
+            / dt
+    }
+
+    pub fn reaction_torque(&self, dt: Real) -> Real {
+        self.impulse.y / dt
+    }
+
+    pub fn translation(&self, bodies: &[Body]) -> Real {
+        let (a, b) = (&bodies[self.index_a], &bodies[self.index_b]);
+        let d = b.pos + self.local_anchor_b.rotate(b.rotation)
+            - a.pos
+            - self.local_anchor_a.rotate(a.rotation);
+        d.dot(self.local_axis_a.rotate(a.rotation))
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], dt: Real) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        let (ma, mb, ia, ib) = (a.inv_mass, b.inv_mass, a.inv_inertia, b.inv_inertia);
+
+        self.dt = dt;
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let d = b.pos + rb - a.pos - ra;
+
+        // Motor and limits act along the axis
+        self.axis = self.local_axis_a.rotate(a.rotation);
+        self.a1 = (d + ra).cross(self.axis);
+        self.a2 = rb.cross(self.axis);
+        let axial_mass = ma + mb + ia * self.a1 * self.a1 + ib * self.a2 * self.a2;
+        self.axial_mass = if axial_mass > 0. { 1. / axial_mass } else { 0. };
+
+        // The prismatic constraint itself acts across the axis and on the angle
+        self.perp = perpendicular(self.local_axis_a).rotate(a.rotation);
+        self.s1 = (d + ra).cross(self.perp);
+        self.s2 = rb.cross(self.perp);
+        let k11 = ma + mb + ia * self.s1 * self.s1 + ib * self.s2 * self.s2;
+        let k12 = ia * self.s1 + ib * self.s2;
+        let mut k22 = ia + ib;
+        if k22 == 0. {
+            // For bodies with fixed rotation
+            k22 = 1.;
+        }
+        self.k = Mat22::new(Vec2::new(k11, k12), Vec2::new(k12, k22));
+
+        if self.limits.is_some() {
+            self.translation = self.axis.dot(d);
+        } else {
+            self.lower_impulse = 0.;
+            self.upper_impulse = 0.;
+        }
+        if self.motor.is_none() {
+            self.motor_impulse = 0.;
+        }
+
+        // Warm start
+        let axial_impulse = self.motor_impulse + self.lower_impulse - self.upper_impulse;
+        let p = self.perp * self.impulse.x + self.axis * axial_impulse;
+        let la = self.impulse.x * self.s1 + self.impulse.y + axial_impulse * self.a1;
+        let lb = self.impulse.x * self.s2 + self.impulse.y + axial_impulse * self.a2;
+        apply_split_impulse(a, b, p, la, lb);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        if let Some(motor) = self.motor {
+            let c_dot = self.axial_speed(a, b);
+            let impulse = self.axial_mass * (motor.speed - c_dot);
+            let old_impulse = self.motor_impulse;
+            let max_impulse = self.dt * motor.max_force;
+            self.motor_impulse = (old_impulse + impulse).clamp(-max_impulse, max_impulse);
+            let impulse = self.motor_impulse - old_impulse;
+
+            apply_split_impulse(
+                a,
+                b,
+                self.axis * impulse,
+                impulse * self.a1,
+                impulse * self.a2,
+            );
+        }
+
+        if let Some((lower, upper)) = self.limits {
+            // Lower limit. Any remaining gap is allowed to close within this step.
+            let c = self.translation - lower;
+            let c_dot = self.axial_speed(a, b);
+            let impulse = -self.axial_mass * (c_dot + Real::max(c, 0.) / self.dt);
+            let old_impulse = self.lower_impulse;
+            self.lower_impulse = Real::max(old_impulse + impulse, 0.);
+            let impulse = self.lower_impulse - old_impulse;
+
+            apply_split_impulse(
+                a,
+                b,
+                self.axis * impulse,
+                impulse * self.a1,
+                impulse * self.a2,
+            );
+
+            // Upper limit, the same constraint with the sign flipped
+            let c = upper - self.translation;
+            let c_dot = -self.axial_speed(a, b);
+            let impulse = -self.axial_mass * (c_dot + Real::max(c, 0.) / self.dt);
+            let old_impulse = self.upper_impulse;
+            self.upper_impulse = Real::max(old_impulse + impulse, 0.);
+            let impulse = self.upper_impulse - old_impulse;
+
+            apply_split_impulse(
+                a,
+                b,
+                self.axis * -impulse,
+                -impulse * self.a1,
+                -impulse * self.a2,
+            );
+        }
+
+        let c_dot = Vec2::new(
+            self.perp.dot(b.vel - a.vel) + self.s2 * b.ang_vel - self.s1 * a.ang_vel,
+            b.ang_vel - a.ang_vel,
+        );
+        let df = self.k.solve(-c_dot);
+        self.impulse += df;
+
+        let p = self.perp * df.x;
+        let la = df.x * self.s1 + df.y;
+        let lb = df.x * self.s2 + df.y;
+        apply_split_impulse(a, b, p, la, lb);
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        let (ma, mb, ia, ib) = (a.inv_mass, b.inv_mass, a.inv_inertia, b.inv_inertia);
+
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let d = b.pos + rb - a.pos - ra;
+
+        let axis = self.local_axis_a.rotate(a.rotation);
+        let a1 = (d + ra).cross(axis);
+        let a2 = rb.cross(axis);
+        let perp = perpendicular(self.local_axis_a).rotate(a.rotation);
+        let s1 = (d + ra).cross(perp);
+        let s2 = rb.cross(perp);
+
+        let c1 = Vec2::new(perp.dot(d), b.rotation - a.rotation - self.reference_angle);
+        let mut linear_error = c1.x.abs();
+        let angular_error = c1.y.abs();
+
+        let mut limit_error = None;
+        if let Some((lower, upper)) = self.limits {
+            let translation = axis.dot(d);
+            if (upper - lower).abs() < 2. * LINEAR_SLOP {
+                limit_error = Some(translation - lower);
+                linear_error = Real::max(linear_error, (translation - lower).abs());
+            } else if translation <= lower {
+                limit_error = Some(Real::min(translation - lower, 0.));
+                linear_error = Real::max(linear_error, lower - translation);
+            } else if translation >= upper {
+                limit_error = Some(Real::max(translation - upper, 0.));
+                linear_error = Real::max(linear_error, translation - upper);
+            }
+        }
+
+        let k11 = ma + mb + ia * s1 * s1 + ib * s2 * s2;
+        let k12 = ia * s1 + ib * s2;
+        let mut k22 = ia + ib;
+        if k22 == 0. {
+            k22 = 1.;
+        }
+
+        let impulse = match limit_error {
+            Some(c2) => {
+                let k13 = ia * s1 * a1 + ib * s2 * a2;
+                let k23 = ia * a1 + ib * a2;
+                let k33 = ma + mb + ia * a1 * a1 + ib * a2 * a2;
+                let k = Mat33::new(
+                    Vec3::new(k11, k12, k13),
+                    Vec3::new(k12, k22, k23),
+                    Vec3::new(k13, k23, k33),
+                );
+                k.solve33(Vec3::new(-c1.x, -c1.y, -c2))
+            }
+            None => {
+                let k = Mat22::new(Vec2::new(k11, k12), Vec2::new(k12, k22));
+                let impulse = k.solve(-c1);
+                Vec3::new(impulse.x, impulse.y, 0.)
+            }
+        };
+
+        let p = perp * impulse.x + axis * impulse.z;
+        let la = impulse.x * s1 + impulse.y + impulse.z * a1;
+        let lb = impulse.x * s2 + impulse.y + impulse.z * a2;
+
+        a.pos -= p * ma;
+        a.rotation -= ia * la;
+        b.pos += p * mb;
+        b.rotation += ib * lb;
+
+        linear_error <= LINEAR_SLOP && angular_error <= ANGULAR_SLOP
+    }
+
+    fn axial_speed(&self, a: &Body, b: &Body) -> Real {
+        self.axis.dot(b.vel - a.vel) + self.a2 * b.ang_vel - self.a1 * a.ang_vel
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    body::{get_pair_mut, Body},
+    constants::LINEAR_SLOP,
+    handle::BodyHandle,
+    real::Real,
+    vec2::Vec2,
+};
+
+// Ties two bodies together with a rope running over two fixed ground anchors, so that
+// length_a + ratio * length_b stays constant. A ratio above 1 works like a block and tackle.
+pub struct PulleyJoint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub ground_anchor_a: Vec2,
+    pub ground_anchor_b: Vec2,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub length_a: Real,
+    pub length_b: Real,
+    pub ratio: Real,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    pub(crate) index_a: usize,
+    pub(crate) index_b: usize,
+    impulse: Real,
+    ua: Vec2,
+    ub: Vec2,
+    ra: Vec2,
+    rb: Vec2,
+    mass: Real,
+}
+
+impl PulleyJoint {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        ground_anchor_a: Vec2,
+        ground_anchor_b: Vec2,
+        length_a: Real,
+        length_b: Real,
+    ) -> Self {
+        PulleyJoint {
+            body_a,
+            body_b,
+            index_a: 0,
+            index_b: 0,
+            ground_anchor_a,
+            ground_anchor_b,
+            local_anchor_a: Vec2::new(0., 0.),
+            local_anchor_b: Vec2::new(0., 0.),
+            length_a,
+            length_b,
+            ratio: 1.,
+            break_force: None,
+            break_torque: None,
+            impulse: 0.,
+            ua: Vec2::new(0., 0.),
+            ub: Vec2::new(0., 0.),
+            ra: Vec2::new(0., 0.),
+            rb: Vec2::new(0., 0.),
+            mass: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        self.ub * (-self.ratio * self.impulse) / dt
+    }
+
+    // Directions from the ground anchors to the bodies and the effective mass along them
+    fn prepare(&mut self, a: &Body, b: &Body) -> (Real, Real) {
+        self.ra = self.local_anchor_a.rotate(a.rotation);
+        self.rb = self.local_anchor_b.rotate(b.rotation);
+        self.ua = a.pos + self.ra - self.ground_anchor_a;
+        self.ub = b.pos + self.rb - self.ground_anchor_b;
+
+        let length_a = self.ua.magnitude();
+        let length_b = self.ub.magnitude();
+        self.ua = if length_a > 10. * LINEAR_SLOP {
+            self.ua / length_a
+        } else {
+            Vec2::new(0., 0.)
+        };
+        self.ub = if length_b > 10. * LINEAR_SLOP {
+            self.ub / length_b
+        } else {
+            Vec2::new(0., 0.)
+        };
+
+        let ru_a = self.ra.cross(self.ua);
+        let ru_b = self.rb.cross(self.ub);
+        let ma = a.inv_mass + a.inv_inertia * ru_a * ru_a;
+        let mb = b.inv_mass + b.inv_inertia * ru_b * ru_b;
+        let mass = ma + self.ratio * self.ratio * mb;
+        self.mass = if mass > 0. { 1. / mass } else { 0. };
+
+        (length_a, length_b)
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], _dt: Real) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        self.prepare(a, b);
+
+        // Warm start
+        let pa = self.ua * -self.impulse;
+        let pb = self.ub * (-self.ratio * self.impulse);
+        a.apply_impulse(pa, self.ra);
+        b.apply_impulse(pb, self.rb);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        let va = a.velocity_at(self.ra);
+        let vb = b.velocity_at(self.rb);
+        let c_dot = -self.ua.dot(va) - self.ratio * self.ub.dot(vb);
+        let impulse = -self.mass * c_dot;
+        self.impulse += impulse;
+
+        let pa = self.ua * -impulse;
+        let pb = self.ub * (-self.ratio * impulse);
+        a.apply_impulse(pa, self.ra);
+        b.apply_impulse(pb, self.rb);
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        let (length_a, length_b) = self.prepare(a, b);
+
+        let constant = self.length_a + self.ratio * self.length_b;
+        let c = constant - length_a - self.ratio * length_b;
+        let impulse = -self.mass * c;
+
+        let pa = self.ua * -impulse;
+        let pb = self.ub * (-self.ratio * impulse);
+        a.pos += pa * a.inv_mass;
+        a.rotation += a.inv_inertia * self.ra.cross(pa);
+        b.pos += pb * b.inv_mass;
+        b.rotation += b.inv_inertia * self.rb.cross(pb);
+
+        c.abs() < LINEAR_SLOP
+    }
+}
@@ -0,0 +1,210 @@
+use crate::{
+    body::{get_pair_mut, Body},
+    constants::{ANGULAR_SLOP, LINEAR_SLOP, MAX_ANGULAR_CORRECTION},
+    handle::BodyHandle,
+    mat22::Mat22,
+    real::Real,
+    vec2::Vec2,
+};
+
+use super::Motor;
+
+// Pins two bodies together at a shared point while leaving them free to rotate around it.
+// The anchors should be the same world point expressed in each body's local space.
+pub struct RevoluteJoint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    // Relative rotation (b.rotation - a.rotation) that counts as an angle of zero
+    pub reference_angle: Real,
+    // Lower and upper joint angle in radians
+    pub limits: Option<(Real, Real)>,
+    pub motor: Option<Motor>,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    pub(crate) index_a: usize,
+    pub(crate) index_b: usize,
+    impulse: Vec2,
+    motor_impulse: Real,
+    lower_impulse: Real,
+    upper_impulse: Real,
+    ra: Vec2,
+    rb: Vec2,
+    k: Mat22,
+    axial_mass: Real,
+    angle: Real,
+    dt: Real,
+}
+
+impl RevoluteJoint {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        local_anchor_a: Vec2,
+        local_anchor_b: Vec2,
+    ) -> Self {
+        RevoluteJoint {
+            body_a,
+            body_b,
+            index_a: 0,
+            index_b: 0,
+            local_anchor_a,
+            local_anchor_b,
+            reference_angle: 0.,
+            limits: None,
+            motor: None,
+            break_force: None,
+            break_torque: None,
+            impulse: Vec2::new(0., 0.),
+            motor_impulse: 0.,
+            lower_impulse: 0.,
+            upper_impulse: 0.,
+            ra: Vec2::new(0., 0.),
+            rb: Vec2::new(0., 0.),
+            k: Mat22::new(Vec2::new(0., 0.), Vec2::new(0., 0.)),
+            axial_mass: 0.,
+            angle: 0.,
+            dt: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        self.impulse / dt
+    }
+
+    pub fn reaction_torque(&self, dt: Real) -> Real {
+        (self.motor_impulse + self.lower_impulse - self.upper_impulse) / dt
+    }
+
+    pub fn angle(&self, bodies: &[Body]) -> Real {
+        bodies[self.index_b].rotation - bodies[self.index_a].rotation - self.reference_angle
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], dt: Real) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        self.dt = dt;
+        self.ra = self.local_anchor_a.rotate(a.rotation);
+        self.rb = self.local_anchor_b.rotate(b.rotation);
+        self.k = point_mass_matrix(a, b, self.ra, self.rb);
+        self.angle = b.rotation - a.rotation - self.reference_angle;
+
+        let i_sum = a.inv_inertia + b.inv_inertia;
+        self.axial_mass = if i_sum > 0. { 1. / i_sum } else { 0. };
+        let fixed_rotation = i_sum == 0.;
+
+        if self.motor.is_none() || fixed_rotation {
+            self.motor_impulse = 0.;
+        }
+        if self.limits.is_none() || fixed_rotation {
+            self.lower_impulse = 0.;
+            self.upper_impulse = 0.;
+        }
+
+        // Warm start
+        let axial_impulse = self.motor_impulse + self.lower_impulse - self.upper_impulse;
+        a.apply_impulse(-self.impulse, self.ra);
+        a.ang_vel -= a.inv_inertia * axial_impulse;
+        b.apply_impulse(self.impulse, self.rb);
+        b.ang_vel += b.inv_inertia * axial_impulse;
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        let fixed_rotation = a.inv_inertia + b.inv_inertia == 0.;
+
+        if let (Some(motor), false) = (self.motor, fixed_rotation) {
+            let c_dot = b.ang_vel - a.ang_vel - motor.speed;
+            let impulse = -self.axial_mass * c_dot;
+            let old_impulse = self.motor_impulse;
+            let max_impulse = self.dt * motor.max_force;
+            self.motor_impulse = (old_impulse + impulse).clamp(-max_impulse, max_impulse);
+            let impulse = self.motor_impulse - old_impulse;
+
+            a.ang_vel -= a.inv_inertia * impulse;
+            b.ang_vel += b.inv_inertia * impulse;
+        }
+
+        if let (Some((lower, upper)), false) = (self.limits, fixed_rotation) {
+            // Lower limit. Any remaining gap is allowed to close within this step.
+            let c = self.angle - lower;
+            let c_dot = b.ang_vel - a.ang_vel;
+            let impulse = -self.axial_mass * (c_dot + Real::max(c, 0.) / self.dt);
+            let old_impulse = self.lower_impulse;
+            self.lower_impulse = Real::max(old_impulse + impulse, 0.);
+            let impulse = self.lower_impulse - old_impulse;
+
+            a.ang_vel -= a.inv_inertia * impulse;
+            b.ang_vel += b.inv_inertia * impulse;
+
+            // Upper limit, the same constraint with the sign flipped
+            let c = upper - self.angle;
+            let c_dot = a.ang_vel - b.ang_vel;
+            let impulse = -self.axial_mass * (c_dot + Real::max(c, 0.) / self.dt);
+            let old_impulse = self.upper_impulse;
+            self.upper_impulse = Real::max(old_impulse + impulse, 0.);
+            let impulse = self.upper_impulse - old_impulse;
+
+            a.ang_vel += a.inv_inertia * impulse;
+            b.ang_vel -= b.inv_inertia * impulse;
+        }
+
+        // Point constraint
+        let c_dot = b.velocity_at(self.rb) - a.velocity_at(self.ra);
+        let impulse = self.k.solve(-c_dot);
+        self.impulse += impulse;
+
+        a.apply_impulse(-impulse, self.ra);
+        b.apply_impulse(impulse, self.rb);
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        let fixed_rotation = a.inv_inertia + b.inv_inertia == 0.;
+
+        let mut angular_error = 0.;
+        if let (Some((lower, upper)), false) = (self.limits, fixed_rotation) {
+            let angle = b.rotation - a.rotation - self.reference_angle;
+            let c = if (upper - lower).abs() < 2. * ANGULAR_SLOP {
+                (angle - lower).clamp(-MAX_ANGULAR_CORRECTION, MAX_ANGULAR_CORRECTION)
+            } else if angle <= lower {
+                (angle - lower + ANGULAR_SLOP).clamp(-MAX_ANGULAR_CORRECTION, 0.)
+            } else if angle >= upper {
+                (angle - upper - ANGULAR_SLOP).clamp(0., MAX_ANGULAR_CORRECTION)
+            } else {
+                0.
+            };
+
+            let limit_impulse = -self.axial_mass * c;
+            a.rotation -= a.inv_inertia * limit_impulse;
+            b.rotation += b.inv_inertia * limit_impulse;
+            angular_error = c.abs();
+        }
+
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let c = b.pos + rb - a.pos - ra;
+        let position_error = c.magnitude();
+
+        let impulse = -point_mass_matrix(a, b, ra, rb).solve(c);
+
+        a.pos -= impulse * a.inv_mass;
+        a.rotation -= a.inv_inertia * ra.cross(impulse);
+        b.pos += impulse * b.inv_mass;
+        b.rotation += b.inv_inertia * rb.cross(impulse);
+
+        position_error <= LINEAR_SLOP && angular_error <= ANGULAR_SLOP
+    }
+}
+
+// Effective mass of a point-to-point constraint, shared with the weld joint
+pub(super) fn point_mass_matrix(a: &Body, b: &Body, ra: Vec2, rb: Vec2) -> Mat22 {
+    let (ma, mb, ia, ib) = (a.inv_mass, b.inv_mass, a.inv_inertia, b.inv_inertia);
+    let k12 = -ra.y * ra.x * ia - rb.y * rb.x * ib;
+    Mat22::new(
+        Vec2::new(ma + mb + ra.y * ra.y * ia + rb.y * rb.y * ib, k12),
+        Vec2::new(k12, ma + mb + ra.x * ra.x * ia + rb.x * rb.x * ib),
+    )
+}
@@ -0,0 +1,135 @@
+use crate::{
+    body::{get_pair_mut, Body},
+    constants::{LINEAR_SLOP, MAX_LINEAR_CORRECTION},
+    handle::BodyHandle,
+    real::Real,
+    vec2::Vec2,
+};
+
+use super::axial_mass;
+
+// Stops two anchor points from getting further apart than max_length. Unlike the distance
+// joint the rope can go slack.
+pub struct RopeJoint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub max_length: Real,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    pub(crate) index_a: usize,
+    pub(crate) index_b: usize,
+    impulse: Real,
+    u: Vec2,
+    ra: Vec2,
+    rb: Vec2,
+    length: Real,
+    mass: Real,
+    dt: Real,
+}
+
+impl RopeJoint {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        local_anchor_a: Vec2,
+        local_anchor_b: Vec2,
+        max_length: Real,
+    ) -> Self {
+        RopeJoint {
+            body_a,
+            body_b,
+            index_a: 0,
+            index_b: 0,
+            local_anchor_a,
+            local_anchor_b,
+            max_length,
+            break_force: None,
+            break_torque: None,
+            impulse: 0.,
+            u: Vec2::new(0., 0.),
+            ra: Vec2::new(0., 0.),
+            rb: Vec2::new(0., 0.),
+            length: 0.,
+            mass: 0.,
+            dt: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        self.u * self.impulse / dt
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], dt: Real) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        self.dt = dt;
+        self.ra = self.local_anchor_a.rotate(a.rotation);
+        self.rb = self.local_anchor_b.rotate(b.rotation);
+        self.u = b.pos + self.rb - a.pos - self.ra;
+        self.length = self.u.magnitude();
+
+        if self.length > LINEAR_SLOP {
+            self.u /= self.length;
+        } else {
+            self.u = Vec2::new(0., 0.);
+            self.mass = 0.;
+            self.impulse = 0.;
+            return;
+        }
+
+        self.mass = axial_mass(a, b, self.ra, self.rb, self.u);
+
+        // Warm start
+        let p = self.u * self.impulse;
+        a.apply_impulse(-p, self.ra);
+        b.apply_impulse(p, self.rb);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        let c = self.length - self.max_length;
+        let mut c_dot = self.u.dot(b.velocity_at(self.rb) - a.velocity_at(self.ra));
+
+        // While slack, allow the rope to close the gap within this step but no further
+        if c < 0. {
+            c_dot += c / self.dt;
+        }
+
+        // The rope can only pull
+        let impulse = -self.mass * c_dot;
+        let old_impulse = self.impulse;
+        self.impulse = Real::min(0., self.impulse + impulse);
+        let impulse = self.impulse - old_impulse;
+
+        let p = self.u * impulse;
+        a.apply_impulse(-p, self.ra);
+        b.apply_impulse(p, self.rb);
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let mut u = b.pos + rb - a.pos - ra;
+
+        let length = u.magnitude();
+        u.normalize();
+        let c = (length - self.max_length).clamp(0., MAX_LINEAR_CORRECTION);
+
+        // The bodies have moved since the velocity phase, so the mass is recomputed
+        let impulse = -axial_mass(a, b, ra, rb, u) * c;
+        let p = u * impulse;
+
+        a.pos -= p * a.inv_mass;
+        a.rotation -= a.inv_inertia * ra.cross(p);
+        b.pos += p * b.inv_mass;
+        b.rotation += b.inv_inertia * rb.cross(p);
+
+        length - self.max_length < LINEAR_SLOP
+    }
+}
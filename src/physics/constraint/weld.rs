@@ -0,0 +1,188 @@
+use crate::{
+    body::{get_pair_mut, Body},
+    constants::{ANGULAR_SLOP, LINEAR_SLOP},
+    handle::BodyHandle,
+    mat33::{Mat33, Vec3},
+    real::Real,
+    vec2::Vec2,
+};
+
+use super::{revolute::point_mass_matrix, Spring};
+
+// Locks the relative position and rotation of two bodies. With a spring the rotation becomes
+// soft, which lets the pieces flex against each other before they would break apart.
+pub struct WeldJoint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub reference_angle: Real,
+    pub spring: Option<Spring>,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    pub(crate) index_a: usize,
+    pub(crate) index_b: usize,
+    impulse: Vec3,
+    ra: Vec2,
+    rb: Vec2,
+    mass: Mat33,
+    gamma: Real,
+    bias: Real,
+}
+
+impl WeldJoint {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        local_anchor_a: Vec2,
+        local_anchor_b: Vec2,
+    ) -> Self {
+        WeldJoint {
+            body_a,
+            body_b,
+            index_a: 0,
+            index_b: 0,
+            local_anchor_a,
+            local_anchor_b,
+            reference_angle: 0.,
+            spring: None,
+            break_force: None,
+            break_torque: None,
+            impulse: Vec3::new(0., 0., 0.),
+            ra: Vec2::new(0., 0.),
+            rb: Vec2::new(0., 0.),
+            mass: Mat33::new(
+                Vec3::new(0., 0., 0.),
+                Vec3::new(0., 0., 0.),
+                Vec3::new(0., 0., 0.),
+            ),
+            gamma: 0.,
+            bias: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        Vec2::new(self.impulse.x, self.impulse.y) / dt
+    }
+
+    pub fn reaction_torque(&self, dt: Real) -> Real {
+        self.impulse.z / dt
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], dt: Real) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        self.ra = self.local_anchor_a.rotate(a.rotation);
+        self.rb = self.local_anchor_b.rotate(b.rotation);
+        let k = weld_mass_matrix(a, b, self.ra, self.rb);
+
+        self.gamma = 0.;
+        self.bias = 0.;
+        if let Some(spring) = self.spring {
+            self.mass = k.inverse22();
+
+            let mut inv_mass = a.inv_inertia + b.inv_inertia;
+            let mass = if inv_mass > 0. { 1. / inv_mass } else { 0. };
+            let (gamma, beta) = spring.coefficients(mass, dt);
+            let c = b.rotation - a.rotation - self.reference_angle;
+            self.gamma = gamma;
+            self.bias = c * beta;
+
+            inv_mass += self.gamma;
+            self.mass.ez.z = if inv_mass != 0. { 1. / inv_mass } else { 0. };
+        } else if k.ez.z == 0. {
+            self.mass = k.inverse22();
+        } else {
+            self.mass = k.sym_inverse33();
+        }
+
+        // Warm start
+        let p = Vec2::new(self.impulse.x, self.impulse.y);
+        a.vel -= p * a.inv_mass;
+        a.ang_vel -= a.inv_inertia * (self.ra.cross(p) + self.impulse.z);
+        b.vel += p * b.inv_mass;
+        b.ang_vel += b.inv_inertia * (self.rb.cross(p) + self.impulse.z);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        if self.spring.is_some() {
+            let c_dot2 = b.ang_vel - a.ang_vel;
+            let impulse2 = -self.mass.ez.z * (c_dot2 + self.bias + self.gamma * self.impulse.z);
+            self.impulse.z += impulse2;
+
+            a.ang_vel -= a.inv_inertia * impulse2;
+            b.ang_vel += b.inv_inertia * impulse2;
+
+            let c_dot1 = b.velocity_at(self.rb) - a.velocity_at(self.ra);
+            let impulse1 = -self.mass.mul22(c_dot1);
+            self.impulse.x += impulse1.x;
+            self.impulse.y += impulse1.y;
+
+            a.apply_impulse(-impulse1, self.ra);
+            b.apply_impulse(impulse1, self.rb);
+        } else {
+            let c_dot1 = b.velocity_at(self.rb) - a.velocity_at(self.ra);
+            let c_dot2 = b.ang_vel - a.ang_vel;
+            let impulse = -self.mass.mul(Vec3::new(c_dot1.x, c_dot1.y, c_dot2));
+            self.impulse += impulse;
+
+            let p = Vec2::new(impulse.x, impulse.y);
+            a.vel -= p * a.inv_mass;
+            a.ang_vel -= a.inv_inertia * (self.ra.cross(p) + impulse.z);
+            b.vel += p * b.inv_mass;
+            b.ang_vel += b.inv_inertia * (self.rb.cross(p) + impulse.z);
+        }
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let k = weld_mass_matrix(a, b, ra, rb);
+
+        let c1 = b.pos + rb - a.pos - ra;
+        let position_error = c1.magnitude();
+        let mut angular_error = 0.;
+
+        let impulse = if self.spring.is_some() {
+            // Only the position is rigid, the angle is left to the spring
+            let impulse = -k.solve22(c1);
+            Vec3::new(impulse.x, impulse.y, 0.)
+        } else {
+            let c2 = b.rotation - a.rotation - self.reference_angle;
+            angular_error = c2.abs();
+
+            if k.ez.z > 0. {
+                -k.solve33(Vec3::new(c1.x, c1.y, c2))
+            } else {
+                let impulse = -k.solve22(c1);
+                Vec3::new(impulse.x, impulse.y, 0.)
+            }
+        };
+
+        let p = Vec2::new(impulse.x, impulse.y);
+        a.pos -= p * a.inv_mass;
+        a.rotation -= a.inv_inertia * (ra.cross(p) + impulse.z);
+        b.pos += p * b.inv_mass;
+        b.rotation += b.inv_inertia * (rb.cross(p) + impulse.z);
+
+        position_error <= LINEAR_SLOP && angular_error <= ANGULAR_SLOP
+    }
+}
+
+// The revolute point matrix extended with the angular row and column
+fn weld_mass_matrix(a: &Body, b: &Body, ra: Vec2, rb: Vec2) -> Mat33 {
+    let (ia, ib) = (a.inv_inertia, b.inv_inertia);
+    let point = point_mass_matrix(a, b, ra, rb);
+    let k13 = -ra.y * ia - rb.y * ib;
+    let k23 = ra.x * ia + rb.x * ib;
+    Mat33::new(
+        Vec3::new(point.ex.x, point.ex.y, k13),
+        Vec3::new(point.ey.x, point.ey.y, k23),
+        Vec3::new(k13, k23, ia + ib),
+    )
+}
@@ -0,0 +1,211 @@
+use crate::{
+    body::{get_pair_mut, Body},
+    constants::LINEAR_SLOP,
+    handle::BodyHandle,
+    real::Real,
+    vec2::Vec2,
+};
+
+use super::{apply_split_impulse, perpendicular, Motor, Spring};
+
+// A wheel (body b) on a suspension. The wheel can move along an axis fixed in the chassis
+// (body a) against a spring and rotates freely, optionally driven by a motor. Without a
+// spring the wheel slides freely along the axis.
+pub struct WheelJoint {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub local_axis_a: Vec2,
+    pub spring: Option<Spring>,
+    pub motor: Option<Motor>,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    pub(crate) index_a: usize,
+    pub(crate) index_b: usize,
+    impulse: Real,
+    spring_impulse: Real,
+    motor_impulse: Real,
+    ax: Vec2,
+    ay: Vec2,
+    s_ax: Real,
+    s_bx: Real,
+    s_ay: Real,
+    s_by: Real,
+    mass: Real,
+    spring_mass: Real,
+    motor_mass: Real,
+    bias: Real,
+    gamma: Real,
+    dt: Real,
+}
+
+impl WheelJoint {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        local_anchor_a: Vec2,
+        local_anchor_b: Vec2,
+        local_axis_a: Vec2,
+    ) -> Self {
+        WheelJoint {
+            body_a,
+            body_b,
+            index_a: 0,
+            index_b: 0,
+            local_anchor_a,
+            local_anchor_b,
+            local_axis_a: local_axis_a.unit_vector(),
+            spring: Some(Spring::new(2., 0.7)),
+            motor: None,
+            break_force: None,
+            break_torque: None,
+            impulse: 0.,
+            spring_impulse: 0.,
+            motor_impulse: 0.,
+            ax: Vec2::new(0., 0.),
+            ay: Vec2::new(0., 0.),
+            s_ax: 0.,
+            s_bx: 0.,
+            s_ay: 0.,
+            s_by: 0.,
+            mass: 0.,
+            spring_mass: 0.,
+            motor_mass: 0.,
+            bias: 0.,
+            gamma: 0.,
+            dt: 0.,
+        }
+    }
+
+    pub fn reaction_force(&self, dt: Real) -> Vec2 {
+        (self.ay * self.impulse + self.ax * self.spring_impulse) / dt
+    }
+
+    pub fn reaction_torque(&self, dt: Real) -> Real {
+        self.motor_impulse / dt
+    }
+
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], dt: Real) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        let (ma, mb, ia, ib) = (a.inv_mass, b.inv_mass, a.inv_inertia, b.inv_inertia);
+
+        self.dt = dt;
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let d = b.pos + rb - a.pos - ra;
+
+        // Point to line constraint across the axis
+        self.ay = perpendicular(self.local_axis_a).rotate(a.rotation);
+        self.s_ay = (d + ra).cross(self.ay);
+        self.s_by = rb.cross(self.ay);
+        let mass = ma + mb + ia * self.s_ay * self.s_ay + ib * self.s_by * self.s_by;
+        self.mass = if mass > 0. { 1. / mass } else { 0. };
+
+        // Suspension spring along the axis
+        self.ax = self.local_axis_a.rotate(a.rotation);
+        self.s_ax = (d + ra).cross(self.ax);
+        self.s_bx = rb.cross(self.ax);
+        self.spring_mass = 0.;
+        self.bias = 0.;
+        self.gamma = 0.;
+        match self.spring {
+            Some(spring) => {
+                let inv_mass = ma + mb + ia * self.s_ax * self.s_ax + ib * self.s_bx * self.s_bx;
+                if inv_mass > 0. {
+                    let (gamma, beta) = spring.coefficients(1. / inv_mass, dt);
+                    self.gamma = gamma;
+                    self.bias = d.dot(self.ax) * beta;
+
+                    let spring_mass = inv_mass + self.gamma;
+                    self.spring_mass = if spring_mass > 0. {
+                        1. / spring_mass
+                    } else {
+                        0.
+                    };
+                }
+            }
+            None => self.spring_impulse = 0.,
+        }
+
+        if self.motor.is_some() {
+            self.motor_mass = if ia + ib > 0. { 1. / (ia + ib) } else { 0. };
+        } else {
+            self.motor_mass = 0.;
+            self.motor_impulse = 0.;
+        }
+
+        // Warm start
+        let p = self.ay * self.impulse + self.ax * self.spring_impulse;
+        let la = self.impulse * self.s_ay + self.spring_impulse * self.s_ax + self.motor_impulse;
+        let lb = self.impulse * self.s_by + self.spring_impulse * self.s_bx + self.motor_impulse;
+        apply_split_impulse(a, b, p, la, lb);
+    }
+
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+
+        // Spring
+        let c_dot = self.ax.dot(b.vel - a.vel) + self.s_bx * b.ang_vel - self.s_ax * a.ang_vel;
+        let impulse = -self.spring_mass * (c_dot + self.bias + self.gamma * self.spring_impulse);
+        self.spring_impulse += impulse;
+        apply_split_impulse(
+            a,
+            b,
+            self.ax * impulse,
+            impulse * self.s_ax,
+            impulse * self.s_bx,
+        );
+
+        // Motor
+        if let Some(motor) = self.motor {
+            let c_dot = b.ang_vel - a.ang_vel - motor.speed;
+            let impulse = -self.motor_mass * c_dot;
+            let old_impulse = self.motor_impulse;
+            let max_impulse = self.dt * motor.max_force;
+            self.motor_impulse = (old_impulse + impulse).clamp(-max_impulse, max_impulse);
+            let impulse = self.motor_impulse - old_impulse;
+
+            a.ang_vel -= a.inv_inertia * impulse;
+            b.ang_vel += b.inv_inertia * impulse;
+        }
+
+        // Point to line
+        let c_dot = self.ay.dot(b.vel - a.vel) + self.s_by * b.ang_vel - self.s_ay * a.ang_vel;
+        let impulse = -self.mass * c_dot;
+        self.impulse += impulse;
+        apply_split_impulse(
+            a,
+            b,
+            self.ay * impulse,
+            impulse * self.s_ay,
+            impulse * self.s_by,
+        );
+    }
+
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.index_a, self.index_b);
+        let (ma, mb, ia, ib) = (a.inv_mass, b.inv_mass, a.inv_inertia, b.inv_inertia);
+
+        let ra = self.local_anchor_a.rotate(a.rotation);
+        let rb = self.local_anchor_b.rotate(b.rotation);
+        let d = b.pos + rb - a.pos - ra;
+
+        let ay = perpendicular(self.local_axis_a).rotate(a.rotation);
+        let s_ay = (d + ra).cross(ay);
+        let s_by = rb.cross(ay);
+
+        let c = d.dot(ay);
+        let k = ma + mb + ia * s_ay * s_ay + ib * s_by * s_by;
+        let impulse = if k != 0. { -c / k } else { 0. };
+
+        let p = ay * impulse;
+        a.pos -= p * ma;
+        a.rotation -= ia * impulse * s_ay;
+        b.pos += p * mb;
+        b.rotation += ib * impulse * s_by;
+
+        c.abs() <= LINEAR_SLOP
+    }
+}
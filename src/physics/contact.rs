@@ -1,82 +1,233 @@
-use super::{body::Body, vec2::Vec2};
+use super::{
+    body::{get_pair_mut, Body},
+    constants::{BAUMGARTE, LINEAR_SLOP, MAX_LINEAR_CORRECTION, VELOCITY_THRESHOLD},
+    constraint::axial_mass,
+    real::Real,
+    vec2::Vec2,
+};
 
-pub struct Contact<'a> {
-    pub a: &'a mut Body,
-    pub b: &'a mut Body,
+// Where two bodies touch. The normal points from a to b, start is the deepest point of b
+// inside a and end is the deepest point of a inside b. The id tells points of the same
+// manifold apart and stays the same while the same features keep touching.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
     pub start: Vec2,
     pub end: Vec2,
     pub normal: Vec2,
-    pub depth: f32,
+    pub depth: Real,
+    pub id: u32,
 }
 
-impl<'a> Contact<'a> {
-    pub fn new(
-        a: &'a mut Body,
-        b: &'a mut Body,
-        start: Vec2,
-        end: Vec2,
-        normal: Vec2,
-        depth: f32,
-    ) -> Self {
+impl Contact {
+    pub fn new(start: Vec2, end: Vec2, normal: Vec2, depth: Real) -> Self {
         Contact {
-            a,
-            b,
             start,
             end,
             normal,
             depth,
+            id: 0,
+        }
+    }
+
+    // The same contact seen from the other body
+    pub fn flipped(self) -> Self {
+        Contact {
+            start: self.end,
+            end: self.start,
+            normal: -self.normal,
+            depth: self.depth,
+            id: self.id,
+        }
+    }
+}
+
+// Every point where two bodies touch. Circles touch at one point, polygons resting edge to
+// edge touch at two.
+#[derive(Clone, Debug)]
+pub struct Manifold {
+    pub contacts: Vec<Contact>,
+}
+
+impl Manifold {
+    pub fn new(contacts: Vec<Contact>) -> Self {
+        Manifold { contacts }
+    }
+
+    pub fn flipped(self) -> Self {
+        Manifold {
+            contacts: self.contacts.into_iter().map(Contact::flipped).collect(),
         }
     }
+}
+
+impl From<Contact> for Manifold {
+    fn from(contact: Contact) -> Self {
+        Manifold::new(vec![contact])
+    }
+}
+
+// What a World pre-solve hook can change about the contact between two bodies, for one update.
+// Disabled contacts aren't solved, so the bodies pass through each other, but they're still
+// reported in the contact events, with no impulse.
+#[derive(Clone, Copy, Debug)]
+pub struct ContactSettings {
+    pub enabled: bool,
+    // Start out as the lower of the two bodies'
+    pub friction: Real,
+    pub restitution: Real,
+    // Speed friction drags b to relative to a, along normal.normal(). A conveyor belt sets it to
+    // its belt speed.
+    pub tangent_speed: Real,
+}
+
+// A contact between world.bodies[a] and world.bodies[b], solved with the same phases as the
+// joints. The accumulated impulses are carried over to the next update to warm start it.
+#[derive(Clone)]
+pub(crate) struct ContactConstraint {
+    pub a: usize,
+    pub b: usize,
+    pub contact: Contact,
+    pub normal_impulse: Real,
+    pub tangent_impulse: Real,
+    // The bodies weren't touching in the previous update
+    pub is_new: bool,
+    pub friction: Real,
+    pub restitution: Real,
+    pub tangent_speed: Real,
+
+    // contact.end on a and contact.start on b, so the separation can be measured as they move
+    local_end: Vec2,
+    local_start: Vec2,
+    ra: Vec2,
+    rb: Vec2,
+    normal_mass: Real,
+    tangent_mass: Real,
+    velocity_bias: Real,
+}
 
-    pub fn resolve_penetration(&mut self) {
-        if self.a.is_static && self.b.is_static {
-            return;
+impl ContactConstraint {
+    pub fn new(a: usize, b: usize, contact: Contact, bodies: &[Body]) -> Self {
+        let (body_a, body_b) = (&bodies[a], &bodies[b]);
+        ContactConstraint {
+            a,
+            b,
+            contact,
+            normal_impulse: 0.,
+            tangent_impulse: 0.,
+            is_new: false,
+            friction: Real::min(body_a.friction, body_b.friction),
+            restitution: Real::min(body_a.restitution, body_b.restitution),
+            tangent_speed: 0.,
+            local_end: body_a.world_to_local(contact.end),
+            local_start: body_b.world_to_local(contact.start),
+            ra: Vec2::new(0., 0.),
+            rb: Vec2::new(0., 0.),
+            normal_mass: 0.,
+            tangent_mass: 0.,
+            velocity_bias: 0.,
         }
+    }
+
+    // The same constraint with a and b swapped. The impulses stay the same, they're along the
+    // flipped normal and tangent and applied to the other body.
+    pub(crate) fn flip(&mut self) {
+        std::mem::swap(&mut self.a, &mut self.b);
+        std::mem::swap(&mut self.local_end, &mut self.local_start);
+        std::mem::swap(&mut self.ra, &mut self.rb);
+        self.contact = self.contact.flipped();
+    }
+
+    pub(crate) fn is_awake(&self, bodies: &[Body]) -> bool {
+        bodies[self.a].is_awake || bodies[self.b].is_awake
+    }
+
+    // start_velocities are the linear and angular velocities of the bodies at the start of the
+    // update, before any forces were applied
+    pub(crate) fn init_velocity(&mut self, bodies: &mut [Body], start_velocities: &[(Vec2, Real)]) {
+        let (a, b) = get_pair_mut(bodies, self.a, self.b);
+        let normal = self.contact.normal;
+        let tangent = normal.normal();
+
+        self.ra = self.contact.end - a.pos;
+        self.rb = self.contact.start - b.pos;
+        self.normal_mass = axial_mass(a, b, self.ra, self.rb, normal);
+        self.tangent_mass = axial_mass(a, b, self.ra, self.rb, tangent);
+
+        // Only bounce off fast impacts, otherwise resting bodies never settle
+        let velocity_at =
+            |(vel, ang_vel): (Vec2, Real), r: Vec2| vel + Vec2::new(-ang_vel * r.y, ang_vel * r.x);
+        let v_rel = velocity_at(start_velocities[self.b], self.rb)
+            - velocity_at(start_velocities[self.a], self.ra);
+        let v_rel_dot_normal = v_rel.dot(normal);
+        self.velocity_bias = if v_rel_dot_normal < -VELOCITY_THRESHOLD {
+            -self.restitution * v_rel_dot_normal
+        } else {
+            0.
+        };
+    }
 
-        self.a.is_colliding = true;
-        self.b.is_colliding = true;
-        let da: f32 = self.depth / (self.a.inv_mass + self.b.inv_mass) * self.a.inv_mass;
-        let db: f32 = self.depth / (self.a.inv_mass + self.b.inv_mass) * self.b.inv_mass;
+    // Separate from init_velocity, so every contact measures how fast it is approaching before
+    // any warm start impulse changes the velocities
+    pub(crate) fn warm_start(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.a, self.b);
+        let normal = self.contact.normal;
+        let tangent = normal.normal();
 
-        self.a.pos -= self.normal * da;
-        self.b.pos += self.normal * db;
+        let p = normal * self.normal_impulse + tangent * self.tangent_impulse;
+        a.apply_impulse(-p, self.ra);
+        b.apply_impulse(p, self.rb);
     }
 
-    pub fn resolve_collision(&mut self) {
-        self.resolve_penetration();
-
-        let e = f32::min(self.a.restitution, self.b.restitution);
-        let f = f32::min(self.a.friction, self.b.friction);
-
-        // Calculate the relavtive velocity between the bodies
-        let ra = self.end - self.a.pos;
-        let rb = self.start - self.b.pos;
-        let va = self.a.vel + Vec2::new(-self.a.ang_vel * ra.y, self.a.ang_vel * ra.x);
-        let vb = self.b.vel + Vec2::new(-self.b.ang_vel * rb.y, self.b.ang_vel * rb.x);
-        let v_rel = va - vb;
-
-        // Relative velocity along the collision normal
-        let v_rel_dot_normal = v_rel.dot(self.normal);
-        let impulse_direction_n = self.normal;
-        let impulse_magnitude_n = -(1. + e) * v_rel_dot_normal
-            / ((self.a.inv_mass + self.b.inv_mass)
-                + ra.cross(self.normal) * ra.cross(self.normal) * self.a.inv_inertia
-                + rb.cross(self.normal) * rb.cross(self.normal) * self.b.inv_inertia);
-        let jn = impulse_direction_n * impulse_magnitude_n;
-
-        // Relative velocity along the collision tangent
-        let tangent = self.normal.normal();
-        let v_rel_dot_tangent = v_rel.dot(tangent);
-        let impulse_direction_t = tangent;
-        let impulse_magnitude_t = f * -(1. + e) * v_rel_dot_tangent
-            / ((self.a.inv_mass + self.b.inv_mass)
-                + ra.cross(tangent) * ra.cross(tangent) * self.a.inv_inertia
-                + rb.cross(tangent) * rb.cross(tangent) * self.b.inv_inertia);
-        let jt = impulse_direction_t * impulse_magnitude_t;
-
-        let impulse = jn + jt;
-
-        self.a.apply_impulse(impulse, ra);
-        self.b.apply_impulse(-impulse, rb);
+    pub(crate) fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (a, b) = get_pair_mut(bodies, self.a, self.b);
+        let normal = self.contact.normal;
+        let tangent = normal.normal();
+
+        // Friction first, it can't be more than the normal impulse allows
+        let v_rel = b.velocity_at(self.rb) - a.velocity_at(self.ra);
+        let impulse = -self.tangent_mass * (v_rel.dot(tangent) - self.tangent_speed);
+        let max_friction = self.friction * self.normal_impulse;
+        let old_impulse = self.tangent_impulse;
+        self.tangent_impulse = (old_impulse + impulse).clamp(-max_friction, max_friction);
+        let impulse = self.tangent_impulse - old_impulse;
+
+        let p = tangent * impulse;
+        a.apply_impulse(-p, self.ra);
+        b.apply_impulse(p, self.rb);
+
+        // Bodies can only be pushed apart
+        let v_rel = b.velocity_at(self.rb) - a.velocity_at(self.ra);
+        let impulse = -self.normal_mass * (v_rel.dot(normal) - self.velocity_bias);
+        let old_impulse = self.normal_impulse;
+        self.normal_impulse = Real::max(old_impulse + impulse, 0.);
+        let impulse = self.normal_impulse - old_impulse;
+
+        let p = normal * impulse;
+        a.apply_impulse(-p, self.ra);
+        b.apply_impulse(p, self.rb);
+    }
+
+    // Returns true once the bodies are no longer noticeably overlapping
+    pub(crate) fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (a, b) = get_pair_mut(bodies, self.a, self.b);
+        let normal = self.contact.normal;
+
+        let end = a.local_to_world(self.local_end);
+        let start = b.local_to_world(self.local_start);
+        let separation = (start - end).dot(normal);
+        let ra = end - a.pos;
+        let rb = start - b.pos;
+
+        // Leave a little overlap so the contact doesn't flicker on and off
+        let c = (BAUMGARTE * (separation + LINEAR_SLOP)).clamp(-MAX_LINEAR_CORRECTION, 0.);
+        let impulse = -axial_mass(a, b, ra, rb, normal) * c;
+        let p = normal * impulse;
+
+        a.pos -= p * a.inv_mass;
+        a.rotation -= a.inv_inertia * ra.cross(p);
+        b.pos += p * b.inv_mass;
+        b.rotation += b.inv_inertia * rb.cross(p);
+
+        separation >= -3. * LINEAR_SLOP
     }
 }
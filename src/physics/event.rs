@@ -0,0 +1,55 @@
+use crate::{constraint::Joint, contact::Contact, handle::BodyHandle, real::Real};
+
+// Things that happened during the last World::update, for gameplay code to react to
+pub enum Event {
+    // The joint was removed from world.joints. index is where it was at the start of the update.
+    // Boxed, joints are much bigger than the other events.
+    JointBroken {
+        index: usize,
+        joint: Box<Joint>,
+    },
+    // The joint was removed from world.joints along with one of its bodies. Comes before the
+    // update's other events, index is where it was when the body was removed, in the order
+    // they were removed in.
+    JointRemoved {
+        index: usize,
+        joint: Box<Joint>,
+    },
+    // A body started or stopped overlapping a sensor. Two sensors don't report each other.
+    // Removing either body ends the overlap, with the removed body's handle in the event.
+    SensorBegin {
+        sensor: BodyHandle,
+        body: BodyHandle,
+    },
+    SensorEnd {
+        sensor: BodyHandle,
+        body: BodyHandle,
+    },
+    // Two bodies started touching, or were still touching, with the points they touch at. Each
+    // update the bodies are touching and awake gets one or the other. Removing either body ends
+    // the contact, with the removed body's handle in the ContactEnd.
+    ContactBegin {
+        a: BodyHandle,
+        b: BodyHandle,
+        points: Vec<ContactPoint>,
+    },
+    ContactPersist {
+        a: BodyHandle,
+        b: BodyHandle,
+        points: Vec<ContactPoint>,
+    },
+    ContactEnd {
+        a: BodyHandle,
+        b: BodyHandle,
+    },
+}
+
+// The contact's normal points from a to b. The impulses are the total the solver applied at
+// the point during the update, along the normal and along the surface, e.g. to play a louder
+// sound for a harder hit.
+#[derive(Clone, Copy, Debug)]
+pub struct ContactPoint {
+    pub contact: Contact,
+    pub normal_impulse: Real,
+    pub tangent_impulse: Real,
+}
@@ -0,0 +1,31 @@
+// Which bodies a body collides with. Two bodies in the same non-zero group always collide if
+// the group is positive and never if it's negative, e.g. the limbs of one ragdoll. Otherwise
+// they collide if each one's category is in the other's mask, so debris with a mask leaving
+// out its own category doesn't hit other debris.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Filter {
+    // Usually a single bit
+    pub category: u32,
+    pub mask: u32,
+    pub group: i32,
+}
+
+impl Filter {
+    pub fn should_collide(&self, other: &Filter) -> bool {
+        if self.group != 0 && self.group == other.group {
+            return self.group > 0;
+        }
+        self.category & other.mask != 0 && other.category & self.mask != 0
+    }
+}
+
+// In the first category and colliding with everything
+impl Default for Filter {
+    fn default() -> Self {
+        Filter {
+            category: 1,
+            mask: u32::MAX,
+            group: 0,
+        }
+    }
+}
@@ -1,7 +1,7 @@
-use crate::{body::Body, vec2::Vec2};
+use crate::{body::Body, real::Real, vec2::Vec2};
 
 // F = k * |v|^2 * -(unit_vector)
-pub fn generate_drag_force(body: &Body, k: f32) -> Vec2 {
+pub fn generate_drag_force(body: &Body, k: Real) -> Vec2 {
     let mut drag_force = Vec2::new(0., 0.);
 
     // Skip calculation if unecessary
@@ -13,7 +13,7 @@ pub fn generate_drag_force(body: &Body, k: f32) -> Vec2 {
     drag_force
 }
 
-pub fn generate_friction_force(body: &Body, k: f32) -> Vec2 {
+pub fn generate_friction_force(body: &Body, k: Real) -> Vec2 {
     let direction = body.vel.normalized() * -1.;
     let magnitude = k * body.vel.magnitude_squared();
 
@@ -24,9 +24,9 @@ pub fn generate_friction_force(body: &Body, k: f32) -> Vec2 {
 pub fn generate_gravitational_force(
     a: &Body,
     b: &Body,
-    g: f32,
-    min_distance: f32,
-    max_distance: f32,
+    g: Real,
+    min_distance: Real,
+    max_distance: Real,
 ) -> Vec2 {
     let distance = b.pos - a.pos;
     let distance_squared = distance
@@ -37,7 +37,7 @@ pub fn generate_gravitational_force(
     distance * magnitude
 }
 
-pub fn generate_spring_force(body: &Body, anchor: Vec2, rest_length: f32, k: f32) -> Vec2 {
+pub fn generate_spring_force(body: &Body, anchor: Vec2, rest_length: Real, k: Real) -> Vec2 {
     let distance = body.pos - anchor;
     let displacement = distance.magnitude() - rest_length;
 
@@ -47,7 +47,7 @@ pub fn generate_spring_force(body: &Body, anchor: Vec2, rest_length: f32, k: f32
     direction * magnitude
 }
 
-pub fn generate_spring_force_bodies(a: &Body, b: &Body, rest_length: f32, k: f32) -> Vec2 {
+pub fn generate_spring_force_bodies(a: &Body, b: &Body, rest_length: Real, k: Real) -> Vec2 {
     let distance = a.pos - b.pos;
     let displacement = distance.magnitude() - rest_length;
 
@@ -0,0 +1,123 @@
+// Refers to a body in a World for as long as it's there. Unlike an index it stays the same as
+// other bodies are added and removed, and once its body is removed the handle is dead for good,
+// even if another body is added in the same slot.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BodyHandle {
+    slot: usize,
+    generation: u32,
+}
+
+struct Slot {
+    // Bumped every time the slot's body is removed, which kills the handles to it
+    generation: u32,
+    // Where the body is in world.bodies
+    index: Option<usize>,
+}
+
+// Maps handles to the indices the solver works with. When a body is removed the last body
+// takes its place, so only that one body's index changes.
+pub(crate) struct HandleMap {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    // The handle of the body at each index
+    handles: Vec<BodyHandle>,
+}
+
+impl HandleMap {
+    pub fn new() -> Self {
+        HandleMap {
+            slots: vec![],
+            free: vec![],
+            handles: vec![],
+        }
+    }
+
+    // A handle for a body pushed onto the end of world.bodies
+    pub fn insert(&mut self) -> BodyHandle {
+        let index = Some(self.handles.len());
+        let slot = match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot].index = index;
+                slot
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    index,
+                });
+                self.slots.len() - 1
+            }
+        };
+        let handle = BodyHandle {
+            slot,
+            generation: self.slots[slot].generation,
+        };
+        self.handles.push(handle);
+        handle
+    }
+
+    // The index the body had, None if the handle was already dead. world.bodies has to be
+    // swap_removed at the same index.
+    pub fn remove(&mut self, handle: BodyHandle) -> Option<usize> {
+        let index = self.index(handle)?;
+        let slot = &mut self.slots[handle.slot];
+        slot.generation += 1;
+        slot.index = None;
+        self.free.push(handle.slot);
+
+        self.handles.swap_remove(index);
+        if let Some(moved) = self.handles.get(index) {
+            self.slots[moved.slot].index = Some(index);
+        }
+        Some(index)
+    }
+
+    pub fn index(&self, handle: BodyHandle) -> Option<usize> {
+        let slot = self.slots.get(handle.slot)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        slot.index
+    }
+
+    pub fn handles(&self) -> &[BodyHandle] {
+        &self.handles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HandleMap;
+
+    #[test]
+    fn removed_handle_stays_dead() {
+        let mut handles = HandleMap::new();
+        let a = handles.insert();
+        let b = handles.insert();
+        let c = handles.insert();
+
+        assert_eq!(handles.remove(a), Some(0));
+        assert_eq!(handles.index(a), None);
+        assert_eq!(handles.remove(a), None);
+
+        // c took a's index, b didn't move
+        assert_eq!(handles.index(b), Some(1));
+        assert_eq!(handles.index(c), Some(0));
+        assert_eq!(handles.handles(), [c, b]);
+    }
+
+    #[test]
+    fn slot_is_reused_with_a_new_generation() {
+        let mut handles = HandleMap::new();
+        let a = handles.insert();
+        let b = handles.insert();
+        handles.remove(a);
+
+        let d = handles.insert();
+        assert_ne!(d, a);
+        assert_eq!(d.slot, a.slot);
+        assert_eq!(handles.index(a), None);
+        assert_eq!(handles.index(b), Some(0));
+        assert_eq!(handles.index(d), Some(1));
+    }
+}
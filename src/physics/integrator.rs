@@ -0,0 +1,172 @@
+use crate::{
+    body::Body,
+    handle::{BodyHandle, HandleMap},
+    real::Real,
+    vec2::Vec2,
+};
+
+// A force that depends on where the bodies are and how fast they move, like a spring or the
+// pull between two planets. Called with the handle of the body to find the force on and the
+// body itself. The integrators evaluate it again at points within the step, so the closure
+// should only read the bodies it's given.
+pub type ForceField = Box<dyn Fn(BodyHandle, &Body, &FieldBodies) -> Vec2>;
+
+// Every body in the world, moved to wherever the integrator is evaluating the fields
+pub struct FieldBodies<'a> {
+    bodies: &'a [Body],
+    handles: &'a HandleMap,
+}
+
+impl<'a> FieldBodies<'a> {
+    // None once the body has been removed
+    pub fn get(&self, handle: BodyHandle) -> Option<&'a Body> {
+        let index = self.handles.index(handle)?;
+        Some(&self.bodies[index])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BodyHandle, &'a Body)> {
+        self.handles.handles().iter().copied().zip(self.bodies)
+    }
+}
+
+// How World moves bodies under their forces. Contacts and joints are solved the same way
+// whichever is used. Forces added to the bodies before the update are taken to be constant
+// over the step, force fields are evaluated as often as the integrator needs.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Integrator {
+    // Velocity first, then position with the new velocity. First order, one force evaluation,
+    // and orbits and springs don't gain energy over time.
+    #[default]
+    SemiImplicitEuler,
+    // Second order, averages the accelerations at the start and end of the step. Two force
+    // evaluations.
+    VelocityVerlet,
+    // Classic fourth order Runge-Kutta. Four force evaluations.
+    RungeKutta4,
+}
+
+// Updates the velocities of the awake dynamic bodies and clears their forces. The positions are
+// left for after the constraints have changed the velocities, so what's returned for each body
+// is how far its move over the step differs from vel * dt, linear and angular.
+pub(crate) fn integrate_forces(
+    integrator: Integrator,
+    bodies: &mut [Body],
+    handles: &HandleMap,
+    fields: &[ForceField],
+    dt: Real,
+) -> Vec<(Vec2, Real)> {
+    let moving: Vec<usize> = (0..bodies.len())
+        .filter(|&index| !bodies[index].is_static && bodies[index].is_awake)
+        .collect();
+
+    // The forces on the bodies stay the same all step
+    let mut constant = vec![Vec2::new(0., 0.); bodies.len()];
+    for &index in &moving {
+        let body = &mut bodies[index];
+        constant[index] = body.net_force * body.inv_mass;
+        body.ang_acc = body.net_torque * body.inv_inertia;
+        body.clear_forces();
+        body.clear_torque();
+    }
+
+    // Accelerations with the bodies moved to a point within the step. Only cloned when there
+    // are fields to evaluate.
+    let acceleration = |states: Option<&[(Vec2, Vec2)]>, bodies: &[Body]| -> Vec<Vec2> {
+        let mut acc = constant.clone();
+        if fields.is_empty() {
+            return acc;
+        }
+        let mut moved;
+        let bodies = match states {
+            Some(states) => {
+                moved = bodies.to_vec();
+                for (&index, &(pos, vel)) in moving.iter().zip(states) {
+                    moved[index].pos = pos;
+                    moved[index].vel = vel;
+                }
+                &moved[..]
+            }
+            None => bodies,
+        };
+        let field_bodies = FieldBodies { bodies, handles };
+        for &index in &moving {
+            let (handle, body) = (handles.handles()[index], &bodies[index]);
+            for field in fields {
+                acc[index] += field(handle, body, &field_bodies) * body.inv_mass;
+            }
+        }
+        acc
+    };
+
+    let mut drift = vec![(Vec2::new(0., 0.), 0.); bodies.len()];
+    let start: Vec<(Vec2, Vec2)> = (moving.iter())
+        .map(|&index| (bodies[index].pos, bodies[index].vel))
+        .collect();
+    let a_1 = acceleration(None, bodies);
+
+    match integrator {
+        Integrator::SemiImplicitEuler => {
+            for &index in &moving {
+                bodies[index].vel += a_1[index] * dt;
+            }
+        }
+        Integrator::VelocityVerlet => {
+            // The end of the step, the velocity there is only a guess for velocity dependent
+            // forces like drag
+            let end: Vec<(Vec2, Vec2)> = (moving.iter().zip(&start))
+                .map(|(&index, &(pos, vel))| {
+                    let a = a_1[index];
+                    (pos + vel * dt + a * (0.5 * dt * dt), vel + a * dt)
+                })
+                .collect();
+            let a_2 = acceleration(Some(&end), bodies);
+
+            for ((&index, &(pos, vel)), &(end_pos, _)) in moving.iter().zip(&start).zip(&end) {
+                let vel = vel + (a_1[index] + a_2[index]) * (0.5 * dt);
+                drift[index].0 = end_pos - pos - vel * dt;
+                bodies[index].vel = vel;
+            }
+        }
+        Integrator::RungeKutta4 => {
+            // Each stage's state, from the start of the step plus the previous stage's
+            // derivatives over h
+            let stage = |vel: &[Vec2], acc: &[Vec2], h: Real| -> Vec<(Vec2, Vec2)> {
+                (moving.iter().zip(&start).zip(vel))
+                    .map(|((&index, &(pos, v)), &k_vel)| (pos + k_vel * h, v + acc[index] * h))
+                    .collect()
+            };
+            let v_1: Vec<Vec2> = start.iter().map(|&(_, vel)| vel).collect();
+
+            let state_2 = stage(&v_1, &a_1, 0.5 * dt);
+            let a_2 = acceleration(Some(&state_2), bodies);
+            let v_2: Vec<Vec2> = state_2.iter().map(|&(_, vel)| vel).collect();
+
+            let state_3 = stage(&v_2, &a_2, 0.5 * dt);
+            let a_3 = acceleration(Some(&state_3), bodies);
+            let v_3: Vec<Vec2> = state_3.iter().map(|&(_, vel)| vel).collect();
+
+            let state_4 = stage(&v_3, &a_3, dt);
+            let a_4 = acceleration(Some(&state_4), bodies);
+            let v_4: Vec<Vec2> = state_4.iter().map(|&(_, vel)| vel).collect();
+
+            for (k, (&index, &(_, vel))) in moving.iter().zip(&start).enumerate() {
+                let moved = (v_1[k] + (v_2[k] + v_3[k]) * 2. + v_4[k]) * (dt / 6.);
+                let acc = a_1[index] + (a_2[index] + a_3[index]) * 2. + a_4[index];
+                let vel = vel + acc * (dt / 6.);
+                drift[index].0 = moved - vel * dt;
+                bodies[index].vel = vel;
+            }
+        }
+    }
+
+    for &index in &moving {
+        let body = &mut bodies[index];
+        body.acc = a_1[index];
+        body.ang_vel += body.ang_acc * dt;
+        // The angular acceleration is constant, which Verlet and RK4 both integrate exactly
+        if integrator != Integrator::SemiImplicitEuler {
+            drift[index].1 = -0.5 * body.ang_acc * dt * dt;
+        }
+    }
+    drift
+}
@@ -0,0 +1,46 @@
+// Groups bodies that touch or are jointed, directly or through other bodies, with a union
+// find over their indices. Static bodies should be left out of the links, otherwise a floor
+// would put everything resting on it into one island.
+pub(crate) struct Islands {
+    parent: Vec<usize>,
+}
+
+impl Islands {
+    pub fn new(count: usize) -> Self {
+        Islands {
+            parent: (0..count).collect(),
+        }
+    }
+
+    pub fn link(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+        self.parent[a] = b;
+    }
+
+    // The bodies of every island, with each island in index order
+    pub fn groups(&mut self, bodies: impl Iterator<Item = usize>) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = vec![];
+        let mut group_of_root: Vec<Option<usize>> = vec![None; self.parent.len()];
+        for body in bodies {
+            let root = self.find(body);
+            match group_of_root[root] {
+                Some(group) => groups[group].push(body),
+                None => {
+                    group_of_root[root] = Some(groups.len());
+                    groups.push(vec![body]);
+                }
+            }
+        }
+        groups
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parent[index] != index {
+            // Path halving keeps the chains short
+            self.parent[index] = self.parent[self.parent[index]];
+            index = self.parent[index];
+        }
+        index
+    }
+}
@@ -1,9 +1,23 @@
+pub mod aabb;
 pub mod body;
+pub mod broadphase;
+pub mod ccd;
+pub mod chain;
 pub mod collision;
 pub mod constants;
+pub mod constraint;
 pub mod contact;
+pub mod event;
+pub mod filter;
 pub mod force;
+pub mod handle;
+pub mod integrator;
+mod island;
+pub mod mat22;
+pub mod mat33;
 pub mod my_texture;
+pub mod raycast;
+pub mod real;
 pub mod shape;
 pub mod vec2;
 pub mod world;
@@ -0,0 +1,40 @@
+use super::vec2::Vec2;
+
+// 2x2 matrix stored as columns, used by the joint solver for point constraints
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Mat22 {
+    pub ex: Vec2,
+    pub ey: Vec2,
+}
+
+impl Mat22 {
+    pub fn new(ex: Vec2, ey: Vec2) -> Self {
+        Mat22 { ex, ey }
+    }
+
+    // Solve A * x = b without inverting the matrix. Singular matrices give a zero vector.
+    pub fn solve(&self, b: Vec2) -> Vec2 {
+        let (a11, a12, a21, a22) = (self.ex.x, self.ey.x, self.ex.y, self.ey.y);
+        let mut det = a11 * a22 - a12 * a21;
+        if det != 0. {
+            det = 1. / det;
+        }
+        Vec2::new(det * (a22 * b.x - a12 * b.y), det * (a11 * b.y - a21 * b.x))
+    }
+
+    pub fn inverse(&self) -> Mat22 {
+        let (a, b, c, d) = (self.ex.x, self.ey.x, self.ex.y, self.ey.y);
+        let mut det = a * d - b * c;
+        if det != 0. {
+            det = 1. / det;
+        }
+        Mat22::new(Vec2::new(det * d, -det * c), Vec2::new(-det * b, det * a))
+    }
+
+    pub fn mul(&self, v: Vec2) -> Vec2 {
+        Vec2::new(
+            self.ex.x * v.x + self.ey.x * v.y,
+            self.ex.y * v.x + self.ey.y * v.y,
+        )
+    }
+}
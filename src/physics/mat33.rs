@@ -0,0 +1,134 @@
+use std::ops;
+
+use super::{real::Real, vec2::Vec2};
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Vec3 {
+    pub x: Real,
+    pub y: Real,
+    pub z: Real,
+}
+
+impl Vec3 {
+    pub fn new(x: Real, y: Real, z: Real) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    pub fn dot(&self, v: Vec3) -> Real {
+        self.x * v.x + self.y * v.y + self.z * v.z
+    }
+
+    pub fn cross(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * v.z - self.z * v.y,
+            self.z * v.x - self.x * v.z,
+            self.x * v.y - self.y * v.x,
+        )
+    }
+}
+
+impl ops::AddAssign for Vec3 {
+    fn add_assign(&mut self, rhs: Vec3) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+        self.z += rhs.z;
+    }
+}
+
+impl ops::Neg for Vec3 {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+// 3x3 matrix stored as columns, used by joints that constrain position and angle together
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Mat33 {
+    pub ex: Vec3,
+    pub ey: Vec3,
+    pub ez: Vec3,
+}
+
+impl Mat33 {
+    pub fn new(ex: Vec3, ey: Vec3, ez: Vec3) -> Self {
+        Mat33 { ex, ey, ez }
+    }
+
+    // Solve A * x = b. Singular matrices give a zero vector.
+    pub fn solve33(&self, b: Vec3) -> Vec3 {
+        let mut det = self.ex.dot(self.ey.cross(self.ez));
+        if det != 0. {
+            det = 1. / det;
+        }
+        Vec3::new(
+            det * b.dot(self.ey.cross(self.ez)),
+            det * self.ex.dot(b.cross(self.ez)),
+            det * self.ex.dot(self.ey.cross(b)),
+        )
+    }
+
+    // Solve using only the upper left 2x2 block
+    pub fn solve22(&self, b: Vec2) -> Vec2 {
+        let (a11, a12, a21, a22) = (self.ex.x, self.ey.x, self.ex.y, self.ey.y);
+        let mut det = a11 * a22 - a12 * a21;
+        if det != 0. {
+            det = 1. / det;
+        }
+        Vec2::new(det * (a22 * b.x - a12 * b.y), det * (a11 * b.y - a21 * b.x))
+    }
+
+    // Inverse of the upper left 2x2 block, the rest of the matrix is zeroed
+    pub fn inverse22(&self) -> Mat33 {
+        let (a, b, c, d) = (self.ex.x, self.ey.x, self.ex.y, self.ey.y);
+        let mut det = a * d - b * c;
+        if det != 0. {
+            det = 1. / det;
+        }
+        Mat33::new(
+            Vec3::new(det * d, -det * c, 0.),
+            Vec3::new(-det * b, det * a, 0.),
+            Vec3::new(0., 0., 0.),
+        )
+    }
+
+    // Inverse of a symmetric matrix, singular matrices give a zero matrix
+    pub fn sym_inverse33(&self) -> Mat33 {
+        let mut det = self.ex.dot(self.ey.cross(self.ez));
+        if det != 0. {
+            det = 1. / det;
+        }
+
+        let (a11, a12, a13) = (self.ex.x, self.ey.x, self.ez.x);
+        let (a22, a23, a33) = (self.ey.y, self.ez.y, self.ez.z);
+
+        let ex = Vec3::new(
+            det * (a22 * a33 - a23 * a23),
+            det * (a13 * a23 - a12 * a33),
+            det * (a12 * a23 - a13 * a22),
+        );
+        let ey = Vec3::new(
+            ex.y,
+            det * (a11 * a33 - a13 * a13),
+            det * (a13 * a12 - a11 * a23),
+        );
+        let ez = Vec3::new(ex.z, ey.z, det * (a11 * a22 - a12 * a12));
+        Mat33::new(ex, ey, ez)
+    }
+
+    pub fn mul(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.ex.x * v.x + self.ey.x * v.y + self.ez.x * v.z,
+            self.ex.y * v.x + self.ey.y * v.y + self.ez.y * v.z,
+            self.ex.z * v.x + self.ey.z * v.y + self.ez.z * v.z,
+        )
+    }
+
+    pub fn mul22(&self, v: Vec2) -> Vec2 {
+        Vec2::new(
+            self.ex.x * v.x + self.ey.x * v.y,
+            self.ex.y * v.x + self.ey.y * v.y,
+        )
+    }
+}
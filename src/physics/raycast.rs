@@ -0,0 +1,148 @@
+use crate::{body::Body, handle::BodyHandle, real::Real, shape::Shape, vec2::Vec2};
+
+// Where a ray hit a body. distance is from the ray's origin to the point, fraction is how far
+// along the ray that is, from 0 at the origin to 1 at the ray's max distance, and None if the
+// ray has no max distance. The normal points out of the body.
+#[derive(Clone, Copy, Debug)]
+pub struct RaycastHit {
+    pub body: BodyHandle,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub distance: Real,
+    pub fraction: Option<Real>,
+}
+
+// The distance along the ray to where it enters the body, and the normal there. A ray starting
+// inside the body doesn't hit it. direction has to be a unit vector.
+pub fn raycast_body(
+    body: &Body,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: Real,
+) -> Option<(Real, Vec2)> {
+    match body.shape {
+        Shape::Circle(radius) => raycast_circle(body.pos, radius, origin, direction, max_distance),
+        Shape::Polygon(_) | Shape::Box(_, _) => {
+            raycast_polygon(body, origin, direction, max_distance)
+        }
+    }
+}
+
+// Solves |origin + direction * t - centre| = radius for the nearest t
+fn raycast_circle(
+    centre: Vec2,
+    radius: Real,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: Real,
+) -> Option<(Real, Vec2)> {
+    let m = origin - centre;
+    let c = m.magnitude_squared() - radius * radius;
+    if c < 0. {
+        return None;
+    }
+    let b = m.dot(direction);
+    let discriminant = b * b - c;
+    if discriminant < 0. {
+        return None;
+    }
+
+    let t = -b - discriminant.sqrt();
+    if !(0. ..=max_distance).contains(&t) {
+        return None;
+    }
+    let normal = (m + direction * t) * (1. / radius);
+    Some((t, normal))
+}
+
+// Clips the ray against the half plane behind every edge. The ray is inside the polygon
+// between the last edge it crosses going in and the first it crosses going out.
+fn raycast_polygon(
+    body: &Body,
+    origin: Vec2,
+    direction: Vec2,
+    max_distance: Real,
+) -> Option<(Real, Vec2)> {
+    let vertices = body.shape.get_world_verticies(body.rotation, body.pos);
+    let mut lower: Real = 0.;
+    let mut upper = max_distance;
+    let mut entered = None;
+
+    for (i, &vertex) in vertices.iter().enumerate() {
+        let normal = body.shape.edge_at(i, body.rotation, body.pos).normal();
+        // Distance of the origin behind the edge, and how fast the ray closes on it
+        let numerator = normal.dot(vertex - origin);
+        let denominator = normal.dot(direction);
+
+        if denominator == 0. {
+            // Parallel to the edge and in front of it, it never gets in
+            if numerator < 0. {
+                return None;
+            }
+        } else if denominator < 0. && numerator < lower * denominator {
+            lower = numerator / denominator;
+            entered = Some(normal);
+        } else if denominator > 0. && numerator < upper * denominator {
+            upper = numerator / denominator;
+        }
+
+        if upper < lower {
+            return None;
+        }
+    }
+
+    // No edge was crossed going in when the ray starts inside
+    entered.map(|normal| (lower, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::raycast_body;
+    use crate::{body::Body, real::Real, shape::Shape, vec2::Vec2};
+
+    fn close(a: Real, b: Real) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn circle_hit_and_miss() {
+        let circle = Body::new(Shape::Circle(10.), 100., 0., 1., None);
+        let right = Vec2::new(1., 0.);
+
+        let (distance, normal) = raycast_body(&circle, Vec2::new(0., 0.), right, 1000.).unwrap();
+        assert!(close(distance, 90.));
+        assert!(close(normal.x, -1.) && close(normal.y, 0.));
+
+        // Passing above it, pointing away from it, and starting inside it
+        assert!(raycast_body(&circle, Vec2::new(0., 20.), right, 1000.).is_none());
+        assert!(raycast_body(&circle, Vec2::new(0., 0.), -right, 1000.).is_none());
+        assert!(raycast_body(&circle, Vec2::new(100., 0.), right, 1000.).is_none());
+    }
+
+    #[test]
+    fn polygon_hit_and_miss() {
+        let square = Body::new(Shape::Box(20., 20.), 0., 100., 1., None);
+        let down = Vec2::new(0., 1.);
+
+        let (distance, normal) = raycast_body(&square, Vec2::new(5., 0.), down, 1000.).unwrap();
+        assert!(close(distance, 90.));
+        assert!(close(normal.x, 0.) && close(normal.y, -1.));
+
+        assert!(raycast_body(&square, Vec2::new(15., 0.), down, 1000.).is_none());
+        assert!(raycast_body(&square, Vec2::new(5., 0.), -down, 1000.).is_none());
+        assert!(raycast_body(&square, Vec2::new(0., 100.), down, 1000.).is_none());
+    }
+
+    #[test]
+    fn max_distance_cuts_the_ray_off() {
+        let circle = Body::new(Shape::Circle(10.), 100., 0., 1., None);
+        let square = Body::new(Shape::Box(20., 20.), 100., 0., 1., None);
+        let (origin, right) = (Vec2::new(0., 0.), Vec2::new(1., 0.));
+
+        for body in [&circle, &square] {
+            assert!(raycast_body(body, origin, right, 89.).is_none());
+            assert!(raycast_body(body, origin, right, 91.).is_some());
+            assert!(raycast_body(body, origin, right, Real::INFINITY).is_some());
+        }
+    }
+}
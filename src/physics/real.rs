@@ -0,0 +1,12 @@
+// The floating point type used throughout the engine. It's f32 unless the f64 feature is
+// enabled, which scenes that run for a long time, like orbits, need to keep their precision.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+// Constants like PI in Real
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
@@ -1,23 +1,19 @@
-use super::vec2::Vec2;
-
-trait Foo {
-    fn foo(&self) -> i32;
-}
+use super::{aabb::Aabb, real::Real, vec2::Vec2};
 
 #[derive(Clone, Debug)]
 pub enum Shape {
     // Circle(radius)
-    Circle(f32),
+    Circle(Real),
 
     // Polygon(Vec<vertices>)
     Polygon(Vec<Vec2>),
 
     // Box<width, height, x, y>
-    Box(f32, f32),
+    Box(Real, Real),
 }
 
 impl Shape {
-    pub fn calc_inertia(&self, mass: f32) -> f32 {
+    pub fn calc_inertia(&self, mass: Real) -> Real {
         match self {
             // 1/2 * m   r^2
             Shape::Circle(radius) => mass * radius * radius * 0.5,
@@ -41,7 +37,7 @@ impl Shape {
         }
     }
 
-    pub fn get_world_verticies(&self, rotation: f32, pos: Vec2) -> Vec<Vec2> {
+    pub fn get_world_verticies(&self, rotation: Real, pos: Vec2) -> Vec<Vec2> {
         // get_world is a closure that calculates the world verticies for Box and Polygon
         let get_world = || {
             self.get_local_verticies()
@@ -57,7 +53,7 @@ impl Shape {
         }
     }
 
-    pub fn edge_at(&self, index: usize, rotation: f32, pos: Vec2) -> Vec2 {
+    pub fn edge_at(&self, index: usize, rotation: Real, pos: Vec2) -> Vec2 {
         let calc_edge = || {
             let vertices = self.get_world_verticies(rotation, pos);
             let current_i = index;
@@ -71,4 +67,15 @@ impl Shape {
             Shape::Box(_, _) => calc_edge(),
         }
     }
+
+    pub fn aabb(&self, rotation: Real, pos: Vec2) -> Aabb {
+        match self {
+            Shape::Circle(radius) => {
+                let r = Vec2::new(*radius, *radius);
+                Aabb::new(pos - r, pos + r)
+            }
+            Shape::Polygon(_) => Aabb::from_points(&self.get_world_verticies(rotation, pos)),
+            Shape::Box(_, _) => Aabb::from_points(&self.get_world_verticies(rotation, pos)),
+        }
+    }
 }
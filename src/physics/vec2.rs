@@ -1,14 +1,16 @@
 use std::ops;
 
+use super::real::Real;
+
 #[derive(PartialEq, Debug, Clone, Copy)]
 
 pub struct Vec2 {
-    pub x: f32,
-    pub y: f32,
+    pub x: Real,
+    pub y: Real,
 }
 
 impl Vec2 {
-    pub fn new(x: f32, y: f32) -> Self {
+    pub fn new(x: Real, y: Real) -> Self {
         Vec2 { x, y }
     }
 
@@ -22,23 +24,23 @@ impl Vec2 {
         self.y -= vec.y
     }
 
-    pub fn scale(&mut self, n: f32) {
+    pub fn scale(&mut self, n: Real) {
         self.x *= n;
         self.y *= n;
     }
 
-    pub fn rotate(&self, angle: f32) -> Vec2 {
+    pub fn rotate(&self, angle: Real) -> Vec2 {
         let x = self.x * angle.cos() - self.y * angle.sin();
         let y = self.x * angle.sin() + self.y * angle.cos();
         Vec2::new(x, y)
     }
 
-    pub fn magnitude(&self) -> f32 {
-        (f32::powf(self.x, 2.) + f32::powf(self.y, 2.)).sqrt()
+    pub fn magnitude(&self) -> Real {
+        (Real::powf(self.x, 2.) + Real::powf(self.y, 2.)).sqrt()
     }
 
-    pub fn magnitude_squared(&self) -> f32 {
-        f32::powf(self.x, 2.) + f32::powf(self.y, 2.)
+    pub fn magnitude_squared(&self) -> Real {
+        Real::powf(self.x, 2.) + Real::powf(self.y, 2.)
     }
 
     // C++ returns an pointer to Vec2, my code just mutates in place
@@ -76,11 +78,11 @@ impl Vec2 {
         perpendicular
     }
 
-    pub fn dot(&self, v: Vec2) -> f32 {
+    pub fn dot(&self, v: Vec2) -> Real {
         self.x * v.x + self.y * v.y
     }
 
-    pub fn cross(&self, v: Vec2) -> f32 {
+    pub fn cross(&self, v: Vec2) -> Real {
         self.x * v.y - self.y * v.x
     }
 }
@@ -100,27 +102,27 @@ impl ops::Sub for Vec2 {
 }
 
 // Scale
-impl ops::Mul<f32> for Vec2 {
+impl ops::Mul<Real> for Vec2 {
     type Output = Vec2;
 
-    fn mul(self, rhs: f32) -> Self::Output {
+    fn mul(self, rhs: Real) -> Self::Output {
         Vec2::new(self.x * rhs, self.y * rhs)
     }
 }
 
 // Dot product
 impl ops::Mul<Vec2> for Vec2 {
-    type Output = f32;
+    type Output = Real;
 
     fn mul(self, rhs: Vec2) -> Self::Output {
         self.x * rhs.x + self.y * rhs.y
     }
 }
 
-impl ops::Div<f32> for Vec2 {
+impl ops::Div<Real> for Vec2 {
     type Output = Vec2;
 
-    fn div(self, rhs: f32) -> Self::Output {
+    fn div(self, rhs: Real) -> Self::Output {
         Vec2::new(self.x / rhs, self.y / rhs)
     }
 }
@@ -139,22 +141,22 @@ impl ops::SubAssign for Vec2 {
     }
 }
 
-impl ops::SubAssign<f32> for Vec2 {
-    fn sub_assign(&mut self, rhs: f32) {
+impl ops::SubAssign<Real> for Vec2 {
+    fn sub_assign(&mut self, rhs: Real) {
         self.x -= rhs;
         self.y -= rhs;
     }
 }
 
-impl ops::MulAssign<f32> for Vec2 {
-    fn mul_assign(&mut self, rhs: f32) {
+impl ops::MulAssign<Real> for Vec2 {
+    fn mul_assign(&mut self, rhs: Real) {
         self.x *= rhs;
         self.y *= rhs;
     }
 }
 
-impl ops::DivAssign<f32> for Vec2 {
-    fn div_assign(&mut self, rhs: f32) {
+impl ops::DivAssign<Real> for Vec2 {
+    fn div_assign(&mut self, rhs: Real) {
         self.x /= rhs;
         self.y /= rhs;
     }
@@ -164,7 +166,7 @@ impl ops::Neg for Vec2 {
     type Output = Self;
 
     fn neg(self) -> Self::Output {
-        Vec2::new(self.x * -1., self.y * -1.)
+        Vec2::new(-self.x, -self.y)
     }
 }
 
@@ -1,36 +1,396 @@
-use crate::{body::Body, collision, vec2::Vec2};
+use std::cell::{Cell, Ref, RefCell};
 
-use super::constants::PIXELS_PER_METER;
+use crate::{
+    aabb::Aabb,
+    body::{get_pair_mut, Body},
+    broadphase::{BroadPhase, DynamicTree},
+    ccd::time_of_impact,
+    collision,
+    constraint::{axial_mass, Joint},
+    contact::{Contact, ContactConstraint, ContactSettings, Manifold},
+    event::{ContactPoint, Event},
+    handle::{BodyHandle, HandleMap},
+    integrator::{self, FieldBodies, ForceField, Integrator},
+    island::Islands,
+    raycast::{raycast_body, RaycastHit},
+    real::Real,
+    vec2::Vec2,
+};
+
+use super::constants::{
+    ANGULAR_SLEEP_TOLERANCE, LINEAR_SLEEP_TOLERANCE, LINEAR_SLOP, MAX_STEPS, MAX_SUB_STEPS,
+    PIXELS_PER_METER, POSITION_ITERATIONS, TIME_STEP, TIME_TO_SLEEP, VELOCITY_ITERATIONS,
+    VELOCITY_THRESHOLD,
+};
+
+// Decides whether two bodies that pass each other's filters collide
+pub type PairFilter = Box<dyn Fn(BodyHandle, &Body, BodyHandle, &Body) -> bool>;
+
+// Changes the contact between two touching bodies before it's solved
+pub type PreSolve =
+    Box<dyn Fn(BodyHandle, &Body, BodyHandle, &Body, &Manifold, &mut ContactSettings)>;
 
 pub struct World {
-    g: f32,
-    pub bodies: Vec<Body>,
+    g: Real,
+    // Joints, contacts and the broadphase refer to bodies by their index in here, everything
+    // outside the world uses handles. Removing a body moves the last one into its place.
+    pub(crate) bodies: Vec<Body>,
+    handles: HandleMap,
+    pub joints: Vec<Joint>,
+    pub velocity_iterations: usize,
+    pub position_iterations: usize,
+    // Used by step, the defaults are TIME_STEP and MAX_STEPS
+    time_step: Real,
+    pub max_steps: usize,
+    // Semi-implicit Euler unless set
+    pub integrator: Integrator,
+    accumulator: Real,
     forces: Vec<Vec2>,
-    torques: Vec<f32>,
+    torques: Vec<Real>,
+    force_fields: Vec<ForceField>,
+    pair_filter: Option<PairFilter>,
+    pre_solve: Option<PreSolve>,
+    // Only updated when it's used, by check_collisions and the raycasts. They only have &self,
+    // hence the cells.
+    broadphase: RefCell<Box<dyn BroadPhase>>,
+    // Bodies have been added, moved or removed since the broadphase was last updated
+    broadphase_stale: Cell<bool>,
+    // How many bodies the broadphase had in its last update, less the ones removed since. Any
+    // after that haven't been added to it yet.
+    broadphase_len: Cell<usize>,
+    contacts: Vec<ContactConstraint>,
+    // Pairs of a sensor and a body overlapping it, sorted like the contacts
+    sensor_overlaps: Vec<(usize, usize)>,
+    events: Vec<Event>,
+    // Events from removing bodies, held until the next update so it doesn't clear them
+    removal_events: Vec<Event>,
 }
 
 impl World {
-    pub fn new(g: f32) -> Self {
+    pub fn new(g: Real) -> Self {
         World {
             g,
             bodies: vec![],
+            handles: HandleMap::new(),
+            joints: vec![],
+            velocity_iterations: VELOCITY_ITERATIONS,
+            position_iterations: POSITION_ITERATIONS,
+            time_step: TIME_STEP,
+            max_steps: MAX_STEPS,
+            integrator: Integrator::default(),
+            accumulator: 0.,
             forces: vec![],
             torques: vec![],
+            force_fields: vec![],
+            pair_filter: None,
+            pre_solve: None,
+            broadphase: RefCell::new(Box::new(DynamicTree::new())),
+            broadphase_stale: Cell::new(false),
+            broadphase_len: Cell::new(0),
+            contacts: vec![],
+            sensor_overlaps: vec![],
+            events: vec![],
+            removal_events: vec![],
         }
     }
-    pub fn add_body(&mut self, body: Body) {
+    pub fn add_body(&mut self, body: Body) -> BodyHandle {
         self.bodies.push(body);
+        self.broadphase_stale.set(true);
+        self.handles.insert()
+    }
+
+    // Joints attached to the body are removed with it and the bodies it was touching are woken.
+    // Removing joints shifts the index of the joints after them, like remove_joint. The
+    // joints, contacts and overlaps the body ends are reported in the events of the next update.
+    // Returns None if the body was already removed.
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<Body> {
+        let index = self.handles.index(handle)?;
+
+        // While every index still has its handle
+        for pair in self.contacts.chunk_by(|c, d| (c.a, c.b) == (d.a, d.b)) {
+            let (i, j) = (pair[0].a, pair[0].b);
+            if i == index || j == index {
+                let (a, b) = (self.handle(i), self.handle(j));
+                self.removal_events.push(Event::ContactEnd { a, b });
+            }
+        }
+        for &(i, j) in &self.sensor_overlaps {
+            if i == index || j == index {
+                let (sensor, body) = self.sensor_pair(i, j);
+                self.removal_events.push(Event::SensorEnd { sensor, body });
+            }
+        }
+
+        // The broadphase follows the swap below if it has the body, which it can only do if
+        // it has the last body too
+        let broadphase_len = self.broadphase_len.get();
+        if index < broadphase_len && broadphase_len < self.bodies.len() {
+            self.refresh_broadphase();
+        }
+
+        self.handles.remove(handle);
+        let body = self.bodies.swap_remove(index);
+        if index < self.broadphase_len.get() {
+            self.broadphase.get_mut().remove(index);
+            self.broadphase_len.set(self.broadphase_len.get() - 1);
+        }
+        self.broadphase_stale.set(true);
+
+        // The last body moved into the removed one's place, anything referring to it by index
+        // follows
+        let moved = self.bodies.len();
+        let follow = |other: &mut usize| {
+            if *other == moved {
+                *other = index;
+            }
+        };
+
+        // From the back, so a joint's index doesn't shift before it's checked and every event's
+        // index is right once the events before it have been applied
+        let mut woken = vec![];
+        for joint_index in (0..self.joints.len()).rev() {
+            let indices = self.joints[joint_index].indices();
+            if indices.contains(&index) {
+                woken.extend(indices);
+                let joint = self.joints.remove(joint_index);
+                self.removal_events.push(Event::JointRemoved {
+                    index: joint_index,
+                    joint: Box::new(joint),
+                });
+            }
+        }
+        for joint in &mut self.joints {
+            joint.indices_mut().into_iter().for_each(follow);
+        }
+
+        // Contacts and overlaps are kept sorted with the lower index first, so the moved body's
+        // are turned around where that changes and sorted again
+        self.contacts.retain(|contact| {
+            if contact.a == index || contact.b == index {
+                woken.extend([contact.a, contact.b]);
+                return false;
+            }
+            true
+        });
+        for contact in &mut self.contacts {
+            follow(&mut contact.a);
+            follow(&mut contact.b);
+            if contact.a > contact.b {
+                contact.flip();
+            }
+        }
+        self.contacts.sort_by_key(|contact| (contact.a, contact.b));
+
+        self.sensor_overlaps
+            .retain(|&(a, b)| a != index && b != index);
+        for (a, b) in &mut self.sensor_overlaps {
+            follow(a);
+            follow(b);
+            if a > b {
+                std::mem::swap(a, b);
+            }
+        }
+        self.sensor_overlaps.sort_unstable();
+
+        for mut other in woken {
+            if other != index {
+                follow(&mut other);
+                self.bodies[other].wake();
+            }
+        }
+        Some(body)
+    }
+
+    // None once the body has been removed
+    pub fn body(&self, handle: BodyHandle) -> Option<&Body> {
+        let index = self.handles.index(handle)?;
+        Some(&self.bodies[index])
+    }
+
+    // Wakes the body, so a sleeping one moves off with whatever velocity or position it's given
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut Body> {
+        let index = self.handles.index(handle)?;
+        self.broadphase_stale.set(true);
+        let body = &mut self.bodies[index];
+        body.wake();
+        Some(body)
+    }
+
+    // Every body in the world with its handle. They come in the order they were added until a
+    // body is removed, then the last one takes its place.
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &Body)> {
+        self.handles.handles().iter().copied().zip(&self.bodies)
+    }
+
+    // Unlike body_mut this doesn't wake anything, a sleeping body that's changed has to be
+    // woken with Body::wake or the change is lost when it's put back to sleep
+    pub fn bodies_mut(&mut self) -> impl Iterator<Item = (BodyHandle, &mut Body)> {
+        self.broadphase_stale.set(true);
+        self.handles.handles().iter().copied().zip(&mut self.bodies)
+    }
+
+    // The handle of the body at index
+    pub(crate) fn handle(&self, index: usize) -> BodyHandle {
+        self.handles.handles()[index]
+    }
+
+    // Returns the joint's index. Panics if any of the joint's bodies have been removed, or if a
+    // joint between two bodies is given the same body twice. A gear's bodies can repeat.
+    pub fn add_joint<J: Into<Joint>>(&mut self, joint: J) -> usize {
+        let mut joint = joint.into();
+        let bodies = joint.bodies();
+        if let [a, b] = bodies[..] {
+            assert_ne!(a, b, "Joint connects a body to itself");
+        }
+        for (index, handle) in joint.indices_mut().into_iter().zip(bodies) {
+            *index = (self.handles.index(handle)).expect("Joint's body has been removed");
+            self.bodies[*index].wake();
+        }
+        self.joints.push(joint);
+        self.joints.len() - 1
+    }
+
+    // The dynamic tree is used unless another broadphase is set
+    pub fn set_broadphase<B: BroadPhase + 'static>(&mut self, broadphase: B) {
+        self.broadphase = RefCell::new(Box::new(broadphase));
+        self.broadphase_stale.set(true);
+        self.broadphase_len.set(0);
+    }
+
+    // Applied to every awake dynamic body each update, e.g. built from the functions in force
+    // that depend on where the bodies are. Other bodies are looked up by handle in the
+    // FieldBodies, which are where the integrator has moved them to.
+    pub fn add_force_field<F>(&mut self, field: F)
+    where
+        F: Fn(BodyHandle, &Body, &FieldBodies) -> Vec2 + 'static,
+    {
+        self.force_fields.push(Box::new(field));
+    }
+
+    // Called for every pair of bodies close enough to touch, after their filters, with each
+    // body's handle. Returning false lets them pass through each other. Replaces any filter
+    // set before.
+    pub fn set_pair_filter<F>(&mut self, filter: F)
+    where
+        F: Fn(BodyHandle, &Body, BodyHandle, &Body) -> bool + 'static,
+    {
+        self.pair_filter = Some(Box::new(filter));
+    }
+
+    // Called every update for each pair of awake bodies that are touching, before the contact
+    // is solved, e.g. to disable it for a one-way platform or set a conveyor belt's speed. The
+    // handles tell which body is the platform or the belt, and the manifold's normal points
+    // from the first body to the second. Bullets call it too when they're about to hit a
+    // static body, and go through it if it's disabled. Replaces any hook set before.
+    pub fn set_pre_solve<F>(&mut self, pre_solve: F)
+    where
+        F: Fn(BodyHandle, &Body, BodyHandle, &Body, &Manifold, &mut ContactSettings) + 'static,
+    {
+        self.pre_solve = Some(Box::new(pre_solve));
+    }
+
+    // Removing a joint shifts the index of every joint added after it
+    pub fn remove_joint(&mut self, index: usize) -> Joint {
+        let joint = self.joints.remove(index);
+        for index in joint.indices() {
+            self.bodies[index].wake();
+        }
+        joint
     }
 
     pub fn get_bodies(&self) -> Vec<Body> {
         self.bodies.clone()
     }
 
-    pub fn update(&mut self, dt: f32, gravity: bool, wind: bool) {
+    // Hash of the state of every body, for checking that simulations kept in lockstep, e.g.
+    // on two machines in a networked game, haven't drifted apart. The world is deterministic:
+    // the same bodies, joints and calls give bit identical results on the same platform and
+    // build. Different platforms may round sin and cos differently.
+    pub fn state_hash(&self) -> u64 {
+        // FNV-1a, which unlike the std hashers is the same in every Rust version
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+        for body in &self.bodies {
+            for value in [body.pos.x, body.pos.y, body.rotation] {
+                write(&value.to_le_bytes());
+            }
+            for value in [body.vel.x, body.vel.y, body.ang_vel] {
+                write(&value.to_le_bytes());
+            }
+            write(&[body.is_awake as u8]);
+        }
+        write(&(self.joints.len() as u32).to_le_bytes());
+        hash
+    }
+
+    // Events from the most recent update, or every update of the most recent step, starting
+    // with those from bodies removed before it
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn time_step(&self) -> Real {
+        self.time_step
+    }
+
+    // The length of the updates step makes. Panics unless it's positive, step would never
+    // catch up otherwise.
+    pub fn set_time_step(&mut self, time_step: Real) {
+        assert!(time_step > 0., "Time step must be positive");
+        self.time_step = time_step;
+    }
+
+    // Advances by the real time elapsed since the last call, in fixed updates of time_step so
+    // the results don't depend on the frame rate. Time left over is carried to the next call.
+    // Returns the number of updates made.
+    pub fn step(&mut self, elapsed: Real, gravity: bool, wind: bool) -> usize {
+        self.events = std::mem::take(&mut self.removal_events);
+
+        // Time beyond max_steps is dropped, the simulation slows down instead of falling
+        // further and further behind
+        let max_time = self.time_step * self.max_steps as Real;
+        // Time can't go backwards, a negative elapsed would hold back later steps
+        self.accumulator = Real::min(self.accumulator + Real::max(elapsed, 0.), max_time);
+
+        // A little slack, so rounding errors don't hold back a step that is due
+        let mut steps = 0;
+        while self.accumulator >= self.time_step * (1. - 1e-4) {
+            self.advance(self.time_step, gravity, wind);
+            self.accumulator = Real::max(self.accumulator - self.time_step, 0.);
+            steps += 1;
+        }
+        steps
+    }
+
+    // How far between the last two updates the time left over by step is, from 0 to 1. Bodies
+    // rendered at Body::interpolated_pos with it move smoothly even when a frame takes no step.
+    pub fn alpha(&self) -> Real {
+        self.accumulator / self.time_step
+    }
+
+    // A single update of dt seconds
+    pub fn update(&mut self, dt: Real, gravity: bool, wind: bool) {
+        self.events = std::mem::take(&mut self.removal_events);
+        self.advance(dt, gravity, wind);
+    }
+
+    fn advance(&mut self, dt: Real, gravity: bool, wind: bool) {
         for body in &mut self.bodies {
-            if gravity {
+            body.previous_pos = body.pos;
+            body.previous_rotation = body.rotation;
+        }
+
+        for body in &mut self.bodies {
+            // Gravity is added directly since add_force would keep every body awake. Every
+            // other force wakes the body.
+            if gravity && body.is_awake {
                 let weight = Vec2::new(0.0, body.mass * self.g * PIXELS_PER_METER);
-                body.add_force(weight);
+                body.net_force += weight;
             }
             if wind {
                 let wind = Vec2::new(300., 0.);
@@ -45,36 +405,804 @@ impl World {
             }
         }
 
+        // Bounces are worked out from how fast the bodies were approaching before this update's
+        // forces, or every bounce would gain the speed gravity adds over a step
+        let start_velocities: Vec<(Vec2, Real)> = (self.bodies.iter())
+            .map(|body| (body.vel, body.ang_vel))
+            .collect();
+        let drift = integrator::integrate_forces(
+            self.integrator,
+            &mut self.bodies,
+            &self.handles,
+            &self.force_fields,
+            dt,
+        );
+
+        self.check_collisions();
+
+        // Constraints with nothing awake are kept for the islands, but there's nothing to solve
+        let awake_contacts: Vec<usize> = (0..self.contacts.len())
+            .filter(|&k| self.contacts[k].is_awake(&self.bodies))
+            .collect();
+        let contacts = self.pre_solve(&awake_contacts);
+        let joints = self.awake_joints();
+
+        for &k in &contacts {
+            self.contacts[k].init_velocity(&mut self.bodies, &start_velocities);
+        }
+        for &k in &contacts {
+            self.contacts[k].warm_start(&mut self.bodies);
+        }
+        for &k in &joints {
+            self.joints[k].init_velocity(&mut self.bodies, dt);
+        }
+        for _ in 0..self.velocity_iterations {
+            for &k in &joints {
+                self.joints[k].solve_velocity(&mut self.bodies);
+            }
+            for &k in &contacts {
+                self.contacts[k].solve_velocity(&mut self.bodies);
+            }
+        }
+
+        self.report_contacts(&awake_contacts);
+
+        // Broken joints are removed before they get to correct positions. Going from the back
+        // means removing one doesn't shift the index reported for the next.
+        for index in (0..self.joints.len()).rev() {
+            if self.joints[index].is_broken(dt) {
+                let joint = self.joints.remove(index);
+                self.events.push(Event::JointBroken {
+                    index,
+                    joint: Box::new(joint),
+                });
+            }
+        }
+
+        // Where each bullet starts the step, to sweep it from once everything has moved
+        let sweeps: Vec<(usize, Vec2, Real)> = (self.bodies.iter().enumerate())
+            .filter(|(_, body)| body.bullet && !body.sensor && body.is_awake)
+            .map(|(index, body)| (index, body.pos, body.rotation))
+            .collect();
+
+        // The integrator's correction is added on top of the move with the solved velocities
+        for (body, (drift, angular_drift)) in self.bodies.iter_mut().zip(drift) {
+            body.integrate_velocities(dt);
+            if body.is_awake {
+                body.pos += drift;
+                body.rotation += angular_drift;
+            }
+        }
+
+        // Joints may have broken since
+        let joints = self.awake_joints();
+        for _ in 0..self.position_iterations {
+            let mut solved = true;
+            for &k in &joints {
+                solved &= self.joints[k].solve_position(&mut self.bodies);
+            }
+            for &k in &contacts {
+                solved &= self.contacts[k].solve_position(&mut self.bodies);
+            }
+            if solved {
+                break;
+            }
+        }
+
+        self.solve_bullets(dt, sweeps);
+        self.update_sleep(dt);
+        self.broadphase_stale.set(true);
+    }
+
+    fn refresh_broadphase(&self) {
+        if self.broadphase_stale.get() {
+            self.broadphase.borrow_mut().update(&self.bodies);
+            self.broadphase_stale.set(false);
+            self.broadphase_len.set(self.bodies.len());
+        }
+    }
+
+    // The broadphase, updated first if the bodies have changed since it last was
+    fn broadphase(&self) -> Ref<'_, dyn BroadPhase> {
+        self.refresh_broadphase();
+        Ref::map(self.broadphase.borrow(), |broadphase| broadphase.as_ref())
+    }
+
+    // The closest body the ray hits within max_distance of its origin, see raycast_all
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: Real) -> Option<RaycastHit> {
+        self.raycast_all(origin, direction, max_distance)
+            .into_iter()
+            .next()
+    }
+
+    // Every body the ray hits within max_distance of its origin, closest first. max_distance
+    // can be infinite, and a ray with no length or direction hits nothing. Sensors are hit
+    // too, and a body the ray starts inside isn't. Only the bodies the broadphase finds near
+    // the ray are tested, it's brought up to date first if the bodies have changed.
+    pub fn raycast_all(
+        &self,
+        origin: Vec2,
+        direction: Vec2,
+        max_distance: Real,
+    ) -> Vec<RaycastHit> {
+        let direction = direction.unit_vector();
+        if max_distance.is_nan() || max_distance <= 0. || direction == Vec2::new(0., 0.) {
+            return vec![];
+        }
+
+        // from_points skips the NaN from 0 * infinity along an axis the ray doesn't move on
+        let end = origin + direction * max_distance;
+        let aabb = Aabb::from_points(&[origin, end]);
+        let mut nearby = self.broadphase().query_aabb(&aabb, &self.bodies);
+        // In index order, so hits at the same distance come out the same with any broadphase
+        nearby.sort_unstable();
+
+        let mut hits: Vec<RaycastHit> = (nearby.into_iter())
+            .filter_map(|index| {
+                let (distance, normal) =
+                    raycast_body(&self.bodies[index], origin, direction, max_distance)?;
+                Some(RaycastHit {
+                    body: self.handle(index),
+                    point: origin + direction * distance,
+                    normal,
+                    distance,
+                    fraction: max_distance.is_finite().then(|| distance / max_distance),
+                })
+            })
+            .collect();
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+
+    // Runs the pre-solve hook on the contacts of each pair, returning the ones still enabled
+    fn pre_solve(&mut self, contacts: &[usize]) -> Vec<usize> {
+        let Some(pre_solve) = &self.pre_solve else {
+            return contacts.to_vec();
+        };
+
+        let mut enabled = vec![];
+        let pair = |k: usize| (self.contacts[k].a, self.contacts[k].b);
+        let groups: Vec<&[usize]> = contacts.chunk_by(|&k, &l| pair(k) == pair(l)).collect();
+        for group in groups {
+            let first = &self.contacts[group[0]];
+            let mut settings = ContactSettings {
+                enabled: true,
+                friction: first.friction,
+                restitution: first.restitution,
+                tangent_speed: first.tangent_speed,
+            };
+            let manifold = Manifold::new(group.iter().map(|&k| self.contacts[k].contact).collect());
+            let (a, b) = (&self.bodies[first.a], &self.bodies[first.b]);
+            let (handle_a, handle_b) = (self.handle(first.a), self.handle(first.b));
+            pre_solve(handle_a, a, handle_b, b, &manifold, &mut settings);
+
+            for &k in group {
+                let contact = &mut self.contacts[k];
+                if settings.enabled {
+                    contact.friction = settings.friction;
+                    contact.restitution = settings.restitution;
+                    contact.tangent_speed = settings.tangent_speed;
+                    enabled.push(k);
+                } else {
+                    // Nothing to warm start from next update either
+                    contact.normal_impulse = 0.;
+                    contact.tangent_impulse = 0.;
+                }
+            }
+        }
+        enabled
+    }
+
+    // Once the impulses are solved. contacts are the awake ones, sorted like self.contacts so
+    // the points of each pair are next to each other.
+    fn report_contacts(&mut self, contacts: &[usize]) {
+        let pair = |k: usize| (self.contacts[k].a, self.contacts[k].b);
+        for group in contacts.chunk_by(|&k, &l| pair(k) == pair(l)) {
+            let (i, j) = pair(group[0]);
+            let (a, b) = (self.handle(i), self.handle(j));
+            let points = (group.iter())
+                .map(|&k| ContactPoint {
+                    contact: self.contacts[k].contact,
+                    normal_impulse: self.contacts[k].normal_impulse,
+                    tangent_impulse: self.contacts[k].tangent_impulse,
+                })
+                .collect();
+            if self.contacts[group[0]].is_new {
+                self.events.push(Event::ContactBegin { a, b, points });
+            } else {
+                self.events.push(Event::ContactPersist { a, b, points });
+            }
+        }
+    }
+
+    // A joint is solved if any of its bodies are awake, and wakes the rest of them
+    fn awake_joints(&mut self) -> Vec<usize> {
+        let mut awake = vec![];
+        for (k, joint) in self.joints.iter().enumerate() {
+            let bodies = joint.indices();
+            if bodies.iter().any(|&index| self.bodies[index].is_awake) {
+                for index in bodies {
+                    if !self.bodies[index].is_static {
+                        self.bodies[index].is_awake = true;
+                    }
+                }
+                awake.push(k);
+            }
+        }
+        awake
+    }
+
+    // Bodies sleep an island at a time, once every body in it has been still for long enough.
+    // Anything awake in an island wakes the rest of it. Woken bodies keep their sleep_time, so
+    // it's the bodies that are actually moving that keep the island up.
+    fn update_sleep(&mut self, dt: Real) {
         for body in &mut self.bodies {
-            body.update(dt)
+            if !body.is_awake {
+                continue;
+            }
+            if body.vel.magnitude_squared() > LINEAR_SLEEP_TOLERANCE * LINEAR_SLEEP_TOLERANCE
+                || body.ang_vel.abs() > ANGULAR_SLEEP_TOLERANCE
+            {
+                body.sleep_time = 0.;
+            } else {
+                body.sleep_time += dt;
+            }
         }
 
-        self.check_collisions();
+        let mut islands = Islands::new(self.bodies.len());
+        for contact in &self.contacts {
+            if !self.bodies[contact.a].is_static && !self.bodies[contact.b].is_static {
+                islands.link(contact.a, contact.b);
+            }
+        }
+        for joint in &self.joints {
+            let bodies: Vec<usize> = (joint.indices().into_iter())
+                .filter(|&index| !self.bodies[index].is_static)
+                .collect();
+            for pair in bodies.windows(2) {
+                islands.link(pair[0], pair[1]);
+            }
+        }
+
+        let dynamic = (0..self.bodies.len()).filter(|&index| !self.bodies[index].is_static);
+        for island in islands.groups(dynamic) {
+            let bodies = island.iter().map(|&index| &self.bodies[index]);
+            let sleep_time = bodies
+                .clone()
+                .map(|body| body.sleep_time)
+                .fold(Real::MAX, Real::min);
+            if sleep_time >= TIME_TO_SLEEP {
+                for &index in &island {
+                    self.bodies[index].sleep();
+                }
+            } else if bodies.clone().any(|body| body.is_awake) {
+                for &index in &island {
+                    self.bodies[index].is_awake = true;
+                }
+            }
+        }
+    }
+
+    // Bullets are swept against static bodies, so a fast one can't end the step on the other
+    // side of a thin wall. Each time it would, it's moved back to where it hit, bounced off,
+    // and carries on for the rest of the step.
+    fn solve_bullets(&mut self, dt: Real, sweeps: Vec<(usize, Vec2, Real)>) {
+        for (index, mut start_pos, mut start_rotation) in sweeps {
+            let mut remaining = dt;
+            for sub_step in 1..=MAX_SUB_STEPS {
+                let body = &self.bodies[index];
+                let swept = body
+                    .aabb()
+                    .union(&body.shape.aabb(start_rotation, start_pos));
+                let mut hits: Vec<(Real, usize)> = (self.bodies.iter().enumerate())
+                    .filter(|(_, other)| other.is_static && !other.sensor)
+                    .filter(|(_, other)| other.aabb().overlaps(&swept))
+                    .filter(|&(other_index, _)| self.should_collide(index, other_index))
+                    .filter_map(|(other_index, other)| {
+                        let t = time_of_impact(body, start_pos, start_rotation, other)?;
+                        Some((t, other_index))
+                    })
+                    .collect();
+                hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+                let hit = hits.into_iter().find_map(|(t, other_index)| {
+                    let settings =
+                        self.bullet_settings(index, start_pos, start_rotation, t, other_index)?;
+                    Some((t, other_index, settings))
+                });
+                let Some((t, other_index, settings)) = hit else {
+                    break;
+                };
+
+                let (body, other) = get_pair_mut(&mut self.bodies, index, other_index);
+                body.pos = start_pos + (body.pos - start_pos) * t;
+                body.rotation = start_rotation + (body.rotation - start_rotation) * t;
+
+                if let Some((distance, on_other, on_body)) = collision::closest_points(other, body)
+                {
+                    let normal = (on_body - on_other).unit_vector();
+                    let r_other = on_other - other.pos;
+                    let r = on_body - body.pos;
+                    let v_rel_dot_normal = body.velocity_at(r).dot(normal);
+                    if v_rel_dot_normal < 0. {
+                        // Same restitution rule as the contacts
+                        let restitution = if v_rel_dot_normal < -VELOCITY_THRESHOLD {
+                            settings.restitution
+                        } else {
+                            0.
+                        };
+                        let mass = axial_mass(other, body, r_other, r, normal);
+                        let impulse = -(1. + restitution) * v_rel_dot_normal * mass;
+                        body.apply_impulse(normal * impulse, r);
+                    }
+
+                    // The next sweep has to start clear, or it would be taken for a resting
+                    // contact and the rest of the step could still tunnel
+                    body.pos += normal * (2. * LINEAR_SLOP - distance);
+                }
+
+                // Out of sub-steps, it stays where it hit rather than risk tunnelling
+                if sub_step == MAX_SUB_STEPS {
+                    break;
+                }
+                remaining *= 1. - t;
+                start_pos = body.pos;
+                start_rotation = body.rotation;
+                body.pos += body.vel * remaining;
+                body.rotation += body.ang_vel * remaining;
+            }
+        }
+    }
+
+    // The pre-solve hook's settings for a bullet that hits other at t of the step, with the
+    // bullet moved to where it hits. They aren't touching yet, so the contact's depth is minus
+    // the gap between them. None if the hook disables the contact and the bullet goes through.
+    fn bullet_settings(
+        &self,
+        index: usize,
+        start_pos: Vec2,
+        start_rotation: Real,
+        t: Real,
+        other_index: usize,
+    ) -> Option<ContactSettings> {
+        let (body, other) = (&self.bodies[index], &self.bodies[other_index]);
+        let mut settings = ContactSettings {
+            enabled: true,
+            friction: Real::min(body.friction, other.friction),
+            restitution: Real::min(body.restitution, other.restitution),
+            tangent_speed: 0.,
+        };
+        let Some(pre_solve) = &self.pre_solve else {
+            return Some(settings);
+        };
+
+        let mut moved = body.clone();
+        moved.pos = start_pos + (body.pos - start_pos) * t;
+        moved.rotation = start_rotation + (body.rotation - start_rotation) * t;
+
+        // Lower index first, like the contacts the hook is usually given
+        let (i, j, a, b) = if index < other_index {
+            (index, other_index, &moved, other)
+        } else {
+            (other_index, index, other, &moved)
+        };
+        let manifold = match collision::closest_points(a, b) {
+            Some((distance, on_a, on_b)) => {
+                let normal = (on_b - on_a).unit_vector();
+                Manifold::from(Contact::new(on_b, on_a, normal, -distance))
+            }
+            None => collision::is_colliding(a, b).unwrap_or(Manifold::new(vec![])),
+        };
+        pre_solve(
+            self.handle(i),
+            a,
+            self.handle(j),
+            b,
+            &manifold,
+            &mut settings,
+        );
+        settings.enabled.then_some(settings)
     }
 
+    fn should_collide(&self, i: usize, j: usize) -> bool {
+        let (a, b) = (&self.bodies[i], &self.bodies[j]);
+        a.filter.should_collide(&b.filter)
+            && (self.pair_filter.as_ref())
+                .is_none_or(|filter| filter(self.handle(i), a, self.handle(j), b))
+    }
+
+    // Gathers every touching pair into self.contacts for the solver. Impulses from a pair that
+    // was already touching last update are kept to warm start it.
     pub fn check_collisions(&mut self) {
         for body in &mut self.bodies {
             body.is_colliding = false;
         }
 
-        for i in 0..self.bodies.len() {
-            for j in (i + 1)..self.bodies.len() {
-                if i != j {
-                    // This is required to get past the borrow checker. Rust doesn't allow two mutable
-                    // references to the vec. So self.bodies is split into two slices with split_at_mut.
-                    // See markdown for more explanation.
-                    let (left, right) = self.bodies.split_at_mut(i + 1);
-                    let maybe_contact =
-                        collision::is_colliding(&mut left[i], &mut right[j - i - 1]);
-
-                    // is_colliding doesn't mutate a and b directly. But they need to be passed as mutable
-                    // references since they will be used in instantiate a Contact class, which has mutable
-                    // references to bodies. I'm not sure if this is the must idiomatic Rust way.
-                    if let Some(mut contact) = maybe_contact {
-                        contact.resolve_collision();
+        let previous = std::mem::take(&mut self.contacts);
+        let previous_overlaps = std::mem::take(&mut self.sensor_overlaps);
+
+        // Only pairs whose boxes overlap are tested, sorted so previous stays sorted too
+        let mut pairs: Vec<(usize, usize)> = self
+            .broadphase()
+            .pairs(&self.bodies)
+            .into_iter()
+            .filter(|&(i, j)| i != j)
+            .map(|(i, j)| (i.min(j), i.max(j)))
+            .collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+
+        for (i, j) in pairs {
+            let (a, b) = (&self.bodies[i], &self.bodies[j]);
+            if (a.is_static && b.is_static) || !self.should_collide(i, j) {
+                continue;
+            }
+
+            // Sensors only need to know whether they overlap, and don't wake what they touch
+            if a.sensor || b.sensor {
+                if a.sensor && b.sensor {
+                    continue;
+                }
+                // Tested even when both are asleep, a sensor can be added next to a sleeping body
+                if collision::is_colliding(a, b).is_some() {
+                    self.sensor_overlaps.push((i, j));
+                    self.bodies[i].is_colliding = true;
+                    self.bodies[j].is_colliding = true;
+                }
+                continue;
+            }
+
+            // Nothing has moved since they fell asleep, so the contacts are carried over as
+            // they were. They aren't solved, they only hold the island together.
+            let first = previous.partition_point(|c| (c.a, c.b) < (i, j));
+            if !a.is_awake && !b.is_awake {
+                let old = previous[first..]
+                    .iter()
+                    .take_while(|c| (c.a, c.b) == (i, j));
+                self.contacts.extend(old.cloned());
+                if self.contacts.last().is_some_and(|c| (c.a, c.b) == (i, j)) {
+                    self.bodies[i].is_colliding = true;
+                    self.bodies[j].is_colliding = true;
+                }
+                continue;
+            }
+
+            if let Some(manifold) = collision::is_colliding(a, b) {
+                let is_new = previous.get(first).is_none_or(|c| (c.a, c.b) != (i, j));
+
+                // A point keeps its impulses if the same features are still touching
+                for contact in manifold.contacts {
+                    let mut constraint = ContactConstraint::new(i, j, contact, &self.bodies);
+                    constraint.is_new = is_new;
+                    let old = previous[first..]
+                        .iter()
+                        .take_while(|c| (c.a, c.b) == (i, j))
+                        .find(|c| c.contact.id == contact.id);
+                    if let Some(old) = old {
+                        constraint.normal_impulse = old.normal_impulse;
+                        constraint.tangent_impulse = old.tangent_impulse;
+                    }
+                    self.contacts.push(constraint);
+                }
+
+                self.bodies[i].is_colliding = true;
+                self.bodies[j].is_colliding = true;
+
+                // Touching an awake body wakes a sleeping one
+                for index in [i, j] {
+                    if !self.bodies[index].is_awake {
+                        self.bodies[index].wake();
                     }
                 }
             }
         }
+
+        for pair in previous.chunk_by(|c, d| (c.a, c.b) == (d.a, d.b)) {
+            let (i, j) = (pair[0].a, pair[0].b);
+            let first = self.contacts.partition_point(|c| (c.a, c.b) < (i, j));
+            if self
+                .contacts
+                .get(first)
+                .is_none_or(|c| (c.a, c.b) != (i, j))
+            {
+                let (a, b) = (self.handle(i), self.handle(j));
+                self.events.push(Event::ContactEnd { a, b });
+            }
+        }
+
+        // Both lists are sorted, so an overlap that's in only one of them began or ended
+        for &(i, j) in &self.sensor_overlaps {
+            if previous_overlaps.binary_search(&(i, j)).is_err() {
+                let (sensor, body) = self.sensor_pair(i, j);
+                self.events.push(Event::SensorBegin { sensor, body });
+            }
+        }
+        for &(i, j) in &previous_overlaps {
+            if self.sensor_overlaps.binary_search(&(i, j)).is_err() {
+                let (sensor, body) = self.sensor_pair(i, j);
+                self.events.push(Event::SensorEnd { sensor, body });
+            }
+        }
+    }
+
+    // The handles of the sensor and the other body of an overlap
+    fn sensor_pair(&self, i: usize, j: usize) -> (BodyHandle, BodyHandle) {
+        let (sensor, body) = if self.bodies[i].sensor {
+            (i, j)
+        } else {
+            (j, i)
+        };
+        (self.handle(sensor), self.handle(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::World;
+    use crate::{
+        body::Body,
+        broadphase::{SpatialHash, SweepAndPrune},
+        chain::Chain,
+        constraint::RevoluteJoint,
+        event::Event,
+        force,
+        handle::BodyHandle,
+        real::Real,
+        shape::Shape,
+        vec2::Vec2,
+    };
+
+    const STEPS: usize = 300;
+
+    fn scene() -> World {
+        let mut world = World::new(9.81);
+        world.add_body(Body::new(Shape::Box(1200., 50.), 600., 750., 0., None));
+        world.add_body(Body::new(Shape::Box(50., 800.), 0., 400., 0., None));
+        world.add_body(Body::new(Shape::Box(50., 800.), 1200., 400., 0., None));
+
+        for i in 0..6 {
+            world.add_body(Body::crate_(300., 680. - 76. * i as Real));
+        }
+        for i in 0..10 {
+            let mut ball = Body::basketball(500. + 40. * i as Real, 100. + 13. * i as Real);
+            ball.vel = Vec2::new(-50. * i as Real, 0.);
+            world.add_body(ball);
+        }
+
+        let anchor = world.add_body(Body::new(Shape::Circle(5.), 900., 100., 0., None));
+        let mut chain = Chain::new(
+            Vec2::new(900., 100.),
+            Vec2::new(1100., 100.),
+            8,
+            Shape::Circle(5.),
+        );
+        chain.start_body = Some(anchor);
+        chain.build(&mut world).unwrap();
+        world
+    }
+
+    fn run(mut world: World) -> u64 {
+        for _ in 0..STEPS {
+            world.update(1. / 60., true, false);
+        }
+        world.state_hash()
+    }
+
+    #[test]
+    fn same_scene_gives_same_state() {
+        let first = run(scene());
+        let second = run(scene());
+        assert_eq!(first, second);
+        assert_ne!(first, scene().state_hash());
+    }
+
+    // A perfectly elastic ball keeps bouncing, but contacts mustn't add energy to it
+    #[test]
+    fn bounce_never_rises_above_drop_height() {
+        let mut world = World::new(9.81);
+        world.add_body(Body::new(Shape::Box(1200., 50.), 600., 750., 0., None));
+        let ball = world.add_body(Body::new(Shape::Circle(20.), 600., 300., 1., None));
+        assert_eq!(world.body(ball).unwrap().restitution, 1.);
+
+        for _ in 0..3000 {
+            world.update(1. / 60., true, false);
+            assert!(world.body(ball).unwrap().pos.y >= 300. - 0.01);
+        }
+    }
+
+    // Both joints holding a chain link are reported, with indices that stay right when the
+    // events are applied in order
+    #[test]
+    fn removing_a_body_reports_its_joints() {
+        let mut world = World::new(9.81);
+        let mut chain = Chain::new(
+            Vec2::new(100., 100.),
+            Vec2::new(300., 100.),
+            4,
+            Shape::Circle(5.),
+        );
+        chain.start_body = Some(world.add_body(Body::new(Shape::Circle(5.), 100., 100., 0., None)));
+        let (links, joints) = chain.build(&mut world).unwrap();
+        assert_eq!(joints.len(), 4);
+
+        world.remove_body(links[1]).unwrap();
+        assert_eq!(world.joints.len(), 2);
+        world.update(1. / 60., true, false);
+
+        let mut remaining: Vec<usize> = joints.clone();
+        for event in world.events() {
+            if let Event::JointRemoved { index, .. } = event {
+                remaining.remove(*index);
+            }
+        }
+        assert_eq!(remaining, [joints[0], joints[3]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Joint connects a body to itself")]
+    fn joint_needs_two_bodies() {
+        let mut world = World::new(9.81);
+        let body = world.add_body(Body::crate_(100., 100.));
+        let anchor = Vec2::new(0., 0.);
+        world.add_joint(RevoluteJoint::new(body, body, anchor, anchor));
+    }
+
+    // The field finds the body it pulls towards by handle, wherever it ends up in world.bodies
+    #[test]
+    fn force_field_follows_handles() {
+        let mut world = World::new(0.);
+        let removed = world.add_body(Body::crate_(100., 100.));
+        let sun = world.add_body(Body::new(Shape::Circle(20.), 500., 300., 0., None));
+        let planet = world.add_body(Body::new(Shape::Circle(5.), 300., 300., 1., None));
+        world.remove_body(removed).unwrap();
+
+        world.add_force_field(move |handle, body, bodies| match bodies.get(sun) {
+            Some(sun) if handle == planet => force::generate_spring_force(body, sun.pos, 0., 10.),
+            _ => Vec2::new(0., 0.),
+        });
+        world.update(1. / 60., true, false);
+        assert!(world.body(planet).unwrap().vel.x > 0.);
+    }
+
+    // A bullet gets the pre-solve hook like any other contact, so it can go through a one-way
+    // wall instead of being stopped by CCD
+    #[test]
+    fn bullet_goes_through_disabled_contact() {
+        let mut world = World::new(9.81);
+        world.add_body(Body::new(Shape::Box(50., 800.), 1600., 400., 0., None));
+        let mut ball = Body::bowlingball(300., 400.);
+        ball.bullet = true;
+        ball.vel = Vec2::new(3000., 0.);
+        let ball = world.add_body(ball);
+        world.set_pre_solve(|_, _, _, _, _, settings| settings.enabled = false);
+
+        for _ in 0..60 {
+            world.update(1. / 60., false, false);
+        }
+        assert!(world.body(ball).unwrap().pos.x > 1700.);
+    }
+
+    // Added furthest first, so the order comes from the distances. Bodies added or moved since
+    // the last update are found too.
+    #[test]
+    fn raycast_all_is_closest_first() {
+        let mut world = World::new(9.81);
+        let far = world.add_body(Body::new(Shape::Circle(10.), 300., 0., 0., None));
+        let middle = world.add_body(Body::new(Shape::Box(20., 20.), 200., 0., 0., None));
+        let near = world.add_body(Body::new(Shape::Circle(10.), 500., 0., 0., None));
+        world.body_mut(near).unwrap().pos.x = 100.;
+        let (origin, right) = (Vec2::new(0., 0.), Vec2::new(1., 0.));
+
+        let hits = world.raycast_all(origin, right, Real::INFINITY);
+        let bodies: Vec<_> = hits.iter().map(|hit| hit.body).collect();
+        assert_eq!(bodies, [near, middle, far]);
+        assert!(hits
+            .windows(2)
+            .all(|hits| hits[0].distance < hits[1].distance));
+        assert!(hits.iter().all(|hit| hit.fraction.is_none()));
+        assert_eq!(
+            world.raycast(origin, right, Real::INFINITY).unwrap().body,
+            near
+        );
+
+        // Cut off before the far circle
+        let hits = world.raycast_all(origin, right, 250.);
+        assert_eq!(hits.len(), 2);
+        assert!((hits[1].fraction.unwrap() - 190. / 250.).abs() < 1e-3);
+
+        world.remove_body(near).unwrap();
+        assert_eq!(world.raycast(origin, right, 250.).unwrap().body, middle);
+        assert!(world.raycast_all(origin, right, 0.).is_empty());
+        assert!(world
+            .raycast_all(origin, Vec2::new(0., 0.), 250.)
+            .is_empty());
+    }
+
+    // Each update's contact events between a and b, one letter each
+    fn contact_events(world: &World, a: BodyHandle, b: BodyHandle) -> String {
+        let pair = |x, y| (x, y) == (a, b) || (x, y) == (b, a);
+        (world.events().iter())
+            .filter_map(|event| match *event {
+                Event::ContactBegin { a, b, .. } if pair(a, b) => Some('B'),
+                Event::ContactPersist { a, b, .. } if pair(a, b) => Some('P'),
+                Event::ContactEnd { a, b } if pair(a, b) => Some('E'),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // A ball dropped on the floor begins touching it once, persists while it rests there and
+    // ends when it's removed
+    #[test]
+    fn contact_events_begin_persist_end() {
+        let mut world = World::new(9.81);
+        let floor = world.add_body(Body::new(Shape::Box(1200., 50.), 600., 750., 0., None));
+        let mut ball = Body::new(Shape::Circle(20.), 600., 700., 1., None);
+        ball.restitution = 0.;
+        let ball = world.add_body(ball);
+
+        let mut events = String::new();
+        for _ in 0..60 {
+            world.update(1. / 60., true, false);
+            events += &contact_events(&world, floor, ball);
+        }
+        assert!(events.starts_with("BP"), "{events}");
+        assert!(events[1..].chars().all(|event| event == 'P'), "{events}");
+
+        world.remove_body(ball).unwrap();
+        world.update(1. / 60., true, false);
+        assert_eq!(contact_events(&world, floor, ball), "E");
+        world.update(1. / 60., true, false);
+        assert_eq!(contact_events(&world, floor, ball), "");
+    }
+
+    // Each update's sensor events, one letter each
+    fn sensor_events(world: &World, sensor: BodyHandle, body: BodyHandle) -> String {
+        (world.events().iter())
+            .filter_map(|event| match *event {
+                Event::SensorBegin { sensor: s, body: b } if (s, b) == (sensor, body) => Some('B'),
+                Event::SensorEnd { sensor: s, body: b } if (s, b) == (sensor, body) => Some('E'),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // A ball falling through a sensor begins and ends overlapping it once, and one removed while
+    // inside it ends the overlap
+    #[test]
+    fn sensor_events_begin_end() {
+        let mut world = World::new(9.81);
+        let mut sensor = Body::new(Shape::Box(200., 100.), 600., 300., 0., None);
+        sensor.sensor = true;
+        let sensor = world.add_body(sensor);
+        let falling = world.add_body(Body::new(Shape::Circle(20.), 600., 150., 1., None));
+
+        let mut events = String::new();
+        for _ in 0..120 {
+            world.update(1. / 60., true, false);
+            events += &sensor_events(&world, sensor, falling);
+        }
+        assert_eq!(events, "BE");
+        assert!(world.body(falling).unwrap().pos.y > 400.);
+
+        let inside = world.add_body(Body::new(Shape::Circle(20.), 650., 300., 1., None));
+        world.update(1. / 60., true, false);
+        assert_eq!(sensor_events(&world, sensor, inside), "B");
+        world.remove_body(inside).unwrap();
+        world.update(1. / 60., true, false);
+        assert_eq!(sensor_events(&world, sensor, inside), "E");
+    }
+
+    // The world sorts the broadphase pairs, so which one finds them makes no difference
+    #[test]
+    fn state_does_not_depend_on_broadphase() {
+        let tree = run(scene());
+
+        let mut world = scene();
+        world.set_broadphase(SweepAndPrune::new());
+        assert_eq!(tree, run(world));
+
+        let mut world = scene();
+        world.set_broadphase(SpatialHash::new(60.));
+        assert_eq!(tree, run(world));
     }
 }